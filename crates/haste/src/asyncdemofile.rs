@@ -0,0 +1,251 @@
+use crate::{
+    demofile::{
+        check_demo_header_id, decompress_cmd, parse_cmd_header, split_cmd_buf, CmdHeader,
+        DemoHeader, Error, Result, DEMO_BUFFER_SIZE, DEMO_HEADER_ID_SIZE, MAX_CMD_HEADER_SIZE,
+    },
+    protos::{prost::Message, CDemoFileInfo, EDemoCommands},
+};
+use std::io::SeekFrom;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeek, AsyncSeekExt};
+
+// AsyncDemoFile is an async counterpart of [`crate::demofile::DemoFile`]; it
+// does framing (demo header, cmd headers, cmd data) asynchronously, anything
+// that goes after that (decompression, decoding) is synchronous.
+//
+// NOTE: you should provide a reader that implements buffering (for example
+// tokio::io::BufReader); cmd headers are read byte by byte.
+pub struct AsyncDemoFile<R: AsyncRead + Unpin> {
+    rdr: R,
+    buf: Vec<u8>,
+    demo_header: Option<DemoHeader>,
+    file_info: Option<CDemoFileInfo>,
+}
+
+impl<R: AsyncRead + Unpin> AsyncDemoFile<R> {
+    pub fn from_reader(rdr: R) -> Self {
+        Self {
+            rdr,
+            buf: vec![0u8; DEMO_BUFFER_SIZE],
+            demo_header: None,
+            file_info: None,
+        }
+    }
+
+    // ----
+
+    pub async fn read_demo_header(&mut self) -> Result<&DemoHeader> {
+        debug_assert!(
+            self.demo_header.is_none(),
+            "expected demo header not to have been read"
+        );
+
+        let mut demofilestamp = [0u8; DEMO_HEADER_ID_SIZE];
+        self.rdr.read_exact(&mut demofilestamp).await?;
+        check_demo_header_id(demofilestamp)?;
+
+        let fileinfo_offset = self.rdr.read_i32_le().await?;
+        let spawngroups_offset = self.rdr.read_i32_le().await?;
+
+        Ok(self.demo_header.insert(DemoHeader {
+            demofilestamp,
+            fileinfo_offset,
+            spawngroups_offset,
+        }))
+    }
+
+    // NOTE: demo_header returns none if read_demo_header was not called.
+    #[inline]
+    pub fn demo_header(&self) -> Option<&DemoHeader> {
+        self.demo_header.as_ref()
+    }
+
+    // ----
+
+    pub async fn read_cmd_header(&mut self) -> Result<CmdHeader> {
+        let first = self.rdr.read_u8().await?;
+        self.read_cmd_header_rest(first).await
+    }
+
+    // try_read_cmd_header is similar to read_cmd_header, but it returns none
+    // if the end of the stream is reached right before the cmd header.
+    pub async fn try_read_cmd_header(&mut self) -> Result<Option<CmdHeader>> {
+        let mut first = [0u8; 1];
+        if self.rdr.read(&mut first).await? == 0 {
+            return Ok(None);
+        }
+        self.read_cmd_header_rest(first[0]).await.map(Some)
+    }
+
+    // read_cmd_header_rest collects bytes of the 3 varints that make up cmd
+    // header and hands them over to the sync parser.
+    async fn read_cmd_header_rest(&mut self, first: u8) -> Result<CmdHeader> {
+        let mut buf = [0u8; MAX_CMD_HEADER_SIZE];
+        buf[0] = first;
+        let mut len = 1;
+        let mut varints = (first & 0x80 == 0) as usize;
+        while varints < 3 && len < MAX_CMD_HEADER_SIZE {
+            let byte = self.rdr.read_u8().await?;
+            buf[len] = byte;
+            len += 1;
+            varints += (byte & 0x80 == 0) as usize;
+        }
+        parse_cmd_header(&mut &buf[..len])
+    }
+
+    pub async fn read_cmd(&mut self, cmd_header: &CmdHeader) -> Result<&[u8]> {
+        let (left, right) = split_cmd_buf(cmd_header, &mut self.buf)?;
+        self.rdr.read_exact(left).await?;
+        decompress_cmd(cmd_header, left, right)
+    }
+
+    // read_cmd_raw appends cmd data as is (without decompressing it) to buf.
+    //
+    // NOTE: cmds are bounded by DEMO_BUFFER_SIZE just like in read_cmd; corrupt
+    // cmd headers must not cause huge allocations.
+    pub(crate) async fn read_cmd_raw(
+        &mut self,
+        cmd_header: &CmdHeader,
+        buf: &mut Vec<u8>,
+    ) -> Result<()> {
+        if cmd_header.size as usize > DEMO_BUFFER_SIZE {
+            return Err(Error::OversizedCmd(cmd_header.size));
+        }
+        let start = buf.len();
+        buf.resize(start + cmd_header.size as usize, 0);
+        self.rdr.read_exact(&mut buf[start..]).await?;
+        Ok(())
+    }
+
+    // discard_cmd is a seek-less alternative to skip_cmd; it reads the cmd
+    // and throws it away.
+    pub async fn discard_cmd(&mut self, cmd_header: &CmdHeader) -> Result<()> {
+        let (data, _) = split_cmd_buf(cmd_header, &mut self.buf)?;
+        self.rdr.read_exact(data).await?;
+        Ok(())
+    }
+}
+
+impl<R: AsyncRead + AsyncSeek + Unpin> AsyncDemoFile<R> {
+    pub async fn skip_cmd(&mut self, cmd_header: &CmdHeader) -> Result<()> {
+        self.seek(SeekFrom::Current(cmd_header.size as i64))
+            .await
+            .map(|_| ())
+    }
+
+    #[inline(always)]
+    pub async fn seek(&mut self, pos: SeekFrom) -> Result<u64> {
+        self.rdr.seek(pos).await.map_err(Error::Io)
+    }
+
+    #[inline(always)]
+    pub async fn stream_position(&mut self) -> Result<u64> {
+        self.rdr.stream_position().await.map_err(Error::Io)
+    }
+
+    // ----
+
+    pub async fn read_file_info(&mut self) -> Result<&CDemoFileInfo> {
+        debug_assert!(
+            self.file_info.is_none(),
+            "expected file info not to have been read"
+        );
+
+        let fileinfo_offset = match self.demo_header.as_ref() {
+            Some(demo_header) => demo_header.fileinfo_offset,
+            None => self.read_demo_header().await?.fileinfo_offset,
+        };
+
+        let backup = self.stream_position().await?;
+        self.seek(SeekFrom::Start(fileinfo_offset as u64)).await?;
+
+        let cmd_header = self.read_cmd_header().await?;
+        if cmd_header.command != EDemoCommands::DemFileInfo {
+            return Err(Error::ExpectedCmd(EDemoCommands::DemFileInfo));
+        }
+        let file_info = CDemoFileInfo::decode(self.read_cmd(&cmd_header).await?)?;
+
+        self.seek(SeekFrom::Start(backup)).await?;
+
+        Ok(self.file_info.insert(file_info))
+    }
+
+    // NOTE: file_info will call read_file_info if file info have not been read
+    pub async fn file_info(&mut self) -> Result<&CDemoFileInfo> {
+        if self.file_info.is_none() {
+            self.read_file_info().await
+        } else {
+            Ok(unsafe { self.file_info.as_ref().unwrap_unchecked() })
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::demofile::make_demo;
+
+    #[test]
+    fn test_read_cmd_header() -> Result<()> {
+        let rt = tokio::runtime::Builder::new_current_thread().build()?;
+        rt.block_on(async {
+            let stop = EDemoCommands::DemStop as u8;
+            let packet = EDemoCommands::DemPacket as u8;
+
+            let data = make_demo(|data| {
+                // tick -1 is u32::MAX, it takes 5 bytes
+                data.extend_from_slice(&[packet, 0xff, 0xff, 0xff, 0xff, 0x0f, 2, 1, 2]);
+                data.extend_from_slice(&[stop, 0x80, 0x01, 0]);
+            });
+
+            let mut demo_file = AsyncDemoFile::from_reader(data.as_slice());
+            demo_file.read_demo_header().await?;
+
+            let cmd_header = demo_file.read_cmd_header().await?;
+            assert_eq!(cmd_header.command, EDemoCommands::DemPacket);
+            assert_eq!(cmd_header.tick, -1);
+            assert_eq!(cmd_header.bytes_read, 7);
+            assert_eq!(demo_file.read_cmd(&cmd_header).await?, [1, 2]);
+
+            let cmd_header = demo_file.try_read_cmd_header().await?;
+            assert!(matches!(
+                cmd_header,
+                Some(CmdHeader {
+                    command: EDemoCommands::DemStop,
+                    tick: 128,
+                    ..
+                })
+            ));
+            assert!(demo_file.try_read_cmd_header().await?.is_none());
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_oversized_cmd() -> Result<()> {
+        let rt = tokio::runtime::Builder::new_current_thread().build()?;
+        rt.block_on(async {
+            let cmd_header = CmdHeader {
+                command: EDemoCommands::DemPacket,
+                is_compressed: false,
+                tick: 0,
+                size: DEMO_BUFFER_SIZE as u32 + 1,
+                bytes_read: 0,
+            };
+
+            let mut demo_file = AsyncDemoFile::from_reader([0u8; 8].as_slice());
+            assert!(matches!(
+                demo_file.discard_cmd(&cmd_header).await,
+                Err(Error::OversizedCmd(size)) if size == cmd_header.size
+            ));
+            let mut buf = Vec::new();
+            assert!(matches!(
+                demo_file.read_cmd_raw(&cmd_header, &mut buf).await,
+                Err(Error::OversizedCmd(size)) if size == cmd_header.size
+            ));
+            assert!(buf.is_empty());
+
+            Ok(())
+        })
+    }
+}
@@ -0,0 +1,221 @@
+use crate::{
+    asyncdemofile::AsyncDemoFile,
+    demofile::DemoHeader,
+    entities::EntityContainer,
+    flattenedserializers::{FlattenedSerializerContainer, SerializerCache},
+    gameevents::GameEventList,
+    parser::{NopVisitor, Parser, Visitor},
+    protos::CDemoFileInfo,
+    stringtables::StringTableContainer,
+};
+use std::io::Cursor;
+use tokio::io::{AsyncRead, AsyncSeek};
+
+// AsyncParser reads cmds from an async reader and hands them over to a regular
+// [`Parser`] one by one; awaiting happens only while cmds are being read,
+// decoding of cmds is synchronous and cpu-bound.
+//
+// NOTE: if cmd decoding must not block the runtime, run the parser on a
+// blocking thread pool instead (see tokio::task::spawn_blocking).
+//
+// NOTE: AsyncParser reads replays front to back, it can't seek (there's no
+// run_to_tick); seeking readers only allow to read file info, see
+// [`AsyncParser::file_info`].
+pub struct AsyncParser<R: AsyncRead + Unpin, V: Visitor> {
+    demo_file: AsyncDemoFile<R>,
+    parser: Parser<Cursor<Vec<u8>>, V>,
+}
+
+impl<R: AsyncRead + Unpin, V: Visitor> AsyncParser<R, V> {
+    pub async fn from_reader_with_visitor(rdr: R, visitor: V) -> crate::Result<Self> {
+        let mut demo_file = AsyncDemoFile::from_reader(rdr);
+        let _demo_header = demo_file.read_demo_header().await?;
+        Ok(Self {
+            demo_file,
+            parser: Parser::from_fragment_reader_with_visitor(Cursor::new(Vec::new()), visitor),
+        })
+    }
+
+    pub async fn run_to_end(&mut self) -> crate::Result<()> {
+        while let Some(cmd_header) = self.demo_file.try_read_cmd_header().await? {
+            // NOTE: reuse cmd buffer's allocation.
+            let rdr = self.parser.fragment_reader_mut();
+            rdr.get_mut().clear();
+            rdr.set_position(0);
+            self.demo_file
+                .read_cmd_raw(&cmd_header, rdr.get_mut())
+                .await?;
+
+            self.parser.handle_fragment_cmd(&cmd_header, false)?;
+        }
+        Ok(())
+    }
+
+    // see [`Parser::set_serializer_cache`].
+    #[inline]
+    pub fn set_serializer_cache(&mut self, serializer_cache: SerializerCache) {
+        self.parser.set_serializer_cache(serializer_cache);
+    }
+
+    #[inline]
+    pub fn visitor(&self) -> &V {
+        self.parser.visitor()
+    }
+
+    #[inline]
+    pub fn visitor_mut(&mut self) -> &mut V {
+        self.parser.visitor_mut()
+    }
+
+    #[inline]
+    pub fn into_visitor(self) -> V {
+        self.parser.into_visitor()
+    }
+
+    // NOTE: following methods are public-facing api; do not use them internally
+
+    #[inline]
+    pub fn demo_header(&self) -> &DemoHeader {
+        // SAFETY: it is safe to call unchecked method here becuase Self's
+        // constructor will return an error if demo header could not be read.
+        unsafe { self.demo_file.demo_header().unwrap_unchecked() }
+    }
+
+    #[inline]
+    pub fn tick(&self) -> i32 {
+        self.parser.tick()
+    }
+
+    #[inline]
+    pub fn string_tables(&self) -> Option<&StringTableContainer> {
+        self.parser.string_tables()
+    }
+
+    #[inline]
+    pub fn serializers(&self) -> Option<&FlattenedSerializerContainer> {
+        self.parser.serializers()
+    }
+
+    #[inline]
+    pub fn entities(&self) -> Option<&EntityContainer> {
+        self.parser.entities()
+    }
+
+    #[inline]
+    pub fn game_event_list(&self) -> Option<&GameEventList> {
+        self.parser.game_event_list()
+    }
+}
+
+impl<R: AsyncRead + AsyncSeek + Unpin, V: Visitor> AsyncParser<R, V> {
+    // file_info seeks to the end of the replay to read file info and then
+    // seeks back; see [`AsyncDemoFile::file_info`].
+    #[inline]
+    pub async fn file_info(&mut self) -> crate::Result<&CDemoFileInfo> {
+        self.demo_file.file_info().await.map_err(crate::Error::from)
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncParser<R, NopVisitor> {
+    #[inline]
+    pub async fn from_reader(rdr: R) -> crate::Result<Self> {
+        Self::from_reader_with_visitor(rdr, NopVisitor).await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        demofile::{make_demo, CmdHeader, DEMO_HEADER_ID_SIZE, DEMO_HEADER_SIZE},
+        parser::Context,
+        protos::EDemoCommands,
+    };
+
+    #[derive(Default)]
+    struct CmdVisitor {
+        cmds: Vec<(EDemoCommands, i32)>,
+        tick_ends: usize,
+    }
+
+    impl Visitor for CmdVisitor {
+        fn on_cmd(
+            &mut self,
+            _ctx: &Context,
+            cmd_header: &CmdHeader,
+            _data: &[u8],
+        ) -> crate::Result<()> {
+            self.cmds.push((cmd_header.command, cmd_header.tick));
+            Ok(())
+        }
+
+        fn on_tick_end(&mut self, _ctx: &Context) -> crate::Result<()> {
+            self.tick_ends += 1;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_run_to_end() -> crate::Result<()> {
+        let sync_tick = EDemoCommands::DemSyncTick as u8;
+        let packet = EDemoCommands::DemPacket as u8;
+        let stop = EDemoCommands::DemStop as u8;
+
+        let data = make_demo(|data| {
+            data.extend_from_slice(&[sync_tick, 0, 0, packet, 1, 0, packet, 2, 0, stop, 2, 0]);
+        });
+
+        let rt = tokio::runtime::Builder::new_current_thread().build()?;
+        let visitor = rt.block_on(async {
+            let mut parser =
+                AsyncParser::from_reader_with_visitor(data.as_slice(), CmdVisitor::default())
+                    .await?;
+            parser.run_to_end().await?;
+            assert_eq!(parser.tick(), 2);
+            crate::Result::Ok(parser.into_visitor())
+        })?;
+
+        assert_eq!(
+            visitor.cmds,
+            [
+                (EDemoCommands::DemSyncTick, 0),
+                (EDemoCommands::DemPacket, 1),
+                (EDemoCommands::DemPacket, 2),
+                (EDemoCommands::DemStop, 2),
+            ]
+        );
+        assert_eq!(visitor.tick_ends, 3);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_file_info() -> crate::Result<()> {
+        use crate::protos::prost::Message;
+
+        let stop = EDemoCommands::DemStop as u8;
+        let file_info = CDemoFileInfo {
+            playback_ticks: Some(42),
+            ..Default::default()
+        }
+        .encode_to_vec();
+
+        let mut data = make_demo(|data| {
+            data.extend_from_slice(&[stop, 0, 0]);
+            data.extend_from_slice(&[EDemoCommands::DemFileInfo as u8, 0, file_info.len() as u8]);
+            data.extend_from_slice(&file_info);
+        });
+        // NOTE: file info goes right after the stop cmd.
+        data[DEMO_HEADER_ID_SIZE..DEMO_HEADER_ID_SIZE + 4]
+            .copy_from_slice(&(DEMO_HEADER_SIZE as i32 + 3).to_le_bytes());
+
+        let rt = tokio::runtime::Builder::new_current_thread().build()?;
+        rt.block_on(async {
+            let mut parser = AsyncParser::from_reader(Cursor::new(data)).await?;
+            assert_eq!(parser.file_info().await?.playback_ticks(), 42);
+            // NOTE: file info must not affect the position of the parser.
+            parser.run_to_end().await?;
+            crate::Result::Ok(())
+        })
+    }
+}
@@ -0,0 +1,203 @@
+#[cfg(feature = "send")]
+use crate::flattenedserializers::SerializerCache;
+use crate::parser::Visitor;
+use std::{
+    num::NonZeroUsize,
+    path::Path,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    thread,
+};
+
+// Batch parses many replays in parallel; each replay is parsed to the end on
+// one of the worker threads with a visitor of its own.
+//
+// NOTE: parsers never leave worker threads, thus batch does not require the
+// send feature.
+pub struct Batch {
+    threads: usize,
+    #[cfg(feature = "send")]
+    serializer_cache: Option<SerializerCache>,
+}
+
+impl Default for Batch {
+    fn default() -> Self {
+        Self {
+            threads: thread::available_parallelism()
+                .map(NonZeroUsize::get)
+                .unwrap_or(1),
+            #[cfg(feature = "send")]
+            serializer_cache: None,
+        }
+    }
+}
+
+impl Batch {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // threads sets the number of worker threads; defaults to available
+    // parallelism.
+    #[inline]
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self
+    }
+
+    // serializer_cache makes all parsers share the cache; replays of the same
+    // game build will not re-parse flattened serializers.
+    #[cfg(feature = "send")]
+    #[inline]
+    pub fn serializer_cache(mut self, serializer_cache: SerializerCache) -> Self {
+        self.serializer_cache = Some(serializer_cache);
+        self
+    }
+
+    // run opens each path (see [`crate::open_with_visitor`]) with a visitor
+    // produced by make_visitor, parses it to the end and turns the visitor
+    // into a result with finish. results are in the same order as paths.
+    #[allow(clippy::expect_used)]
+    pub fn run<P, V, T, F, G>(
+        &self,
+        paths: &[P],
+        make_visitor: F,
+        finish: G,
+    ) -> Vec<crate::Result<T>>
+    where
+        P: AsRef<Path> + Sync,
+        V: Visitor,
+        T: Send,
+        F: Fn(&Path) -> V + Sync,
+        G: Fn(V) -> T + Sync,
+    {
+        let next = AtomicUsize::new(0);
+        let results: Mutex<Vec<Option<crate::Result<T>>>> =
+            Mutex::new(paths.iter().map(|_| None).collect());
+
+        let run_one = |path: &Path| -> crate::Result<T> {
+            let mut parser = crate::open_with_visitor(path, make_visitor(path))?;
+            #[cfg(feature = "send")]
+            if let Some(serializer_cache) = self.serializer_cache.as_ref() {
+                parser.set_serializer_cache(serializer_cache.clone());
+            }
+            parser.run_to_end()?;
+            Ok(finish(parser.into_visitor()))
+        };
+
+        thread::scope(|scope| {
+            for _ in 0..self.threads.min(paths.len()) {
+                scope.spawn(|| loop {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(path) = paths.get(i) else {
+                        break;
+                    };
+                    let result = run_one(path.as_ref());
+                    if let Ok(mut results) = results.lock() {
+                        results[i] = Some(result);
+                    }
+                });
+            }
+        });
+
+        // NOTE: results can be missing only if a worker panicked, in which case
+        // thread::scope propagates the panic (before getting here).
+        results
+            .into_inner()
+            .unwrap_or_else(|err| err.into_inner())
+            .into_iter()
+            .map(|result| result.expect("worker did not store a result"))
+            .collect()
+    }
+}
+
+// run is a shortcut for [`Batch::run`] with default number of threads.
+pub fn run<P, V, T, F, G>(paths: &[P], make_visitor: F, finish: G) -> Vec<crate::Result<T>>
+where
+    P: AsRef<Path> + Sync,
+    V: Visitor,
+    T: Send,
+    F: Fn(&Path) -> V + Sync,
+    G: Fn(V) -> T + Sync,
+{
+    Batch::new().run(paths, make_visitor, finish)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        demofile::{make_demo, CmdHeader},
+        parser::Context,
+        protos::EDemoCommands,
+    };
+    use std::path::PathBuf;
+
+    #[derive(Default)]
+    struct CmdCounter(usize);
+
+    impl Visitor for CmdCounter {
+        fn on_cmd(
+            &mut self,
+            _ctx: &Context,
+            _cmd_header: &CmdHeader,
+            _data: &[u8],
+        ) -> crate::Result<()> {
+            self.0 += 1;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_run() -> crate::Result<()> {
+        let sync_tick = EDemoCommands::DemSyncTick as u8;
+        let stop = EDemoCommands::DemStop as u8;
+
+        let dir = std::env::temp_dir().join(format!("haste_batch_{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+
+        let mut paths: Vec<PathBuf> = (0..4)
+            .map(|i| {
+                let data = make_demo(|data| {
+                    for tick in 0..i {
+                        data.extend_from_slice(&[sync_tick, tick, 0]);
+                    }
+                    data.extend_from_slice(&[stop, i, 0]);
+                });
+
+                let path = dir.join(format!("{i}.dem"));
+                std::fs::write(&path, data)?;
+                Ok(path)
+            })
+            .collect::<std::io::Result<_>>()?;
+        paths.push(dir.join("missing.dem"));
+
+        let results =
+            Batch::new()
+                .threads(2)
+                .run(&paths, |_path| CmdCounter::default(), |visitor| visitor.0);
+        std::fs::remove_dir_all(&dir)?;
+
+        assert_eq!(results.len(), 5);
+        let counts: Vec<usize> = results[..4]
+            .iter()
+            .map(|result| *result.as_ref().unwrap_or(&0))
+            .collect();
+        assert_eq!(counts, [1, 2, 3, 4]);
+        assert!(matches!(results[4], Err(crate::Error::DemoSource(_))));
+
+        Ok(())
+    }
+
+    #[cfg(feature = "send")]
+    #[test]
+    fn test_parser_is_send() {
+        use crate::parser::{NopVisitor, Parser};
+
+        fn assert_send<T: Send>() {}
+        assert_send::<Parser<std::fs::File, NopVisitor>>();
+    }
+}
@@ -522,6 +522,78 @@ impl<'d> BitReader<'d> {
     }
 }
 
+// BitWriter is a minimal counterpart of BitReader that is used to prepare test
+// data; bits are written lsb first.
+#[cfg(test)]
+#[derive(Default)]
+pub(crate) struct BitWriter {
+    data: Vec<u8>,
+    bits: usize,
+}
+
+#[cfg(test)]
+impl BitWriter {
+    pub(crate) fn write_bool(&mut self, value: bool) {
+        if self.bits.is_multiple_of(8) {
+            self.data.push(0);
+        }
+        if value {
+            self.data[self.bits / 8] |= 1 << (self.bits % 8);
+        }
+        self.bits += 1;
+    }
+
+    pub(crate) fn write_ubitlong(&mut self, value: u32, num_bits: usize) {
+        (0..num_bits).for_each(|i| self.write_bool(value & (1 << i) != 0));
+    }
+
+    pub(crate) fn write_ubitvar(&mut self, value: u32) {
+        match value {
+            0..16 => self.write_ubitlong(value, 6),
+            16..256 => {
+                self.write_ubitlong((value & 15) | 16, 6);
+                self.write_ubitlong(value >> 4, 4);
+            }
+            256..4096 => {
+                self.write_ubitlong((value & 15) | 32, 6);
+                self.write_ubitlong(value >> 4, 8);
+            }
+            _ => {
+                self.write_ubitlong((value & 15) | 48, 6);
+                self.write_ubitlong(value >> 4, 32 - 4);
+            }
+        }
+    }
+
+    pub(crate) fn write_uvarint32(&mut self, mut value: u32) {
+        while value >= 0x80 {
+            self.write_ubitlong(value & 0x7f | 0x80, 8);
+            value >>= 7;
+        }
+        self.write_ubitlong(value, 8);
+    }
+
+    pub(crate) fn write_bytes(&mut self, bytes: &[u8]) {
+        bytes
+            .iter()
+            .for_each(|byte| self.write_ubitlong(*byte as u32, 8));
+    }
+
+    // NOTE: reader works with u32s; data is padded to make sure that the last
+    // u32 is complete.
+    pub(crate) fn finish(mut self) -> Vec<u8> {
+        self.data.resize(self.data.len().next_multiple_of(4), 0);
+        self.data
+    }
+
+    // finish_unpadded is similar to finish, but it does not pad data; packets
+    // are read until there's less then a byte left, padding would be
+    // interpreted as messages.
+    pub(crate) fn finish_unpadded(self) -> Vec<u8> {
+        self.data
+    }
+}
+
 #[cfg(test)]
 mod test {
 
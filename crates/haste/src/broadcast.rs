@@ -0,0 +1,202 @@
+use crate::{
+    entities::EntityContainer,
+    flattenedserializers::FlattenedSerializerContainer,
+    gameevents::GameEventList,
+    parser::{NopVisitor, Parser, Visitor},
+    stringtables::StringTableContainer,
+};
+use std::io::Cursor;
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    // mod
+    #[error("{0:?} fragment arrived before start fragment")]
+    MissingStart(FragmentType),
+    #[error("delta fragment arrived before full fragment")]
+    MissingFull,
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+// FragmentType is a type of a broadcast fragment; broadcasts (tv_broadcast)
+// are served over http as <base url>/<fragment>/<type>.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FragmentType {
+    // Start is a signon fragment (string tables, send tables, class info,
+    // etc.). there's only one start fragment - the signup fragment.
+    Start,
+    // Full carries a full packet that allows to join the broadcast at any
+    // fragment.
+    Full,
+    // Delta carries packets that follow full packet of the same fragment.
+    Delta,
+}
+
+impl FragmentType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Start => "start",
+            Self::Full => "full",
+            Self::Delta => "delta",
+        }
+    }
+}
+
+// BroadcastParser feeds broadcast fragments into the same machinery that
+// parses demo files; fragments contain the same cmds, but there's no demo
+// header and no trailing DemFileInfo.
+//
+// fragments must be pushed in order: start, full, then deltas of the same and
+// following fragments. pushing another full fragment re-syncs entities (for
+// example if client fell behind and some deltas are gone).
+pub struct BroadcastParser<V: Visitor> {
+    parser: Parser<Cursor<Vec<u8>>, V>,
+    did_handle_start: bool,
+    did_handle_full: bool,
+}
+
+impl<V: Visitor> BroadcastParser<V> {
+    pub fn new_with_visitor(visitor: V) -> Self {
+        Self {
+            parser: Parser::from_fragment_reader_with_visitor(Cursor::new(Vec::new()), visitor),
+            did_handle_start: false,
+            did_handle_full: false,
+        }
+    }
+
+    pub fn push_fragment(&mut self, fragment_type: FragmentType, data: &[u8]) -> crate::Result<()> {
+        match fragment_type {
+            FragmentType::Start => {}
+            _ if !self.did_handle_start => {
+                return Err(Error::MissingStart(fragment_type).into());
+            }
+            FragmentType::Delta if !self.did_handle_full => {
+                return Err(Error::MissingFull.into());
+            }
+            _ => {}
+        }
+
+        // NOTE: reuse fragment buffer's allocation.
+        let rdr = self.parser.fragment_reader_mut();
+        rdr.get_mut().clear();
+        rdr.get_mut().extend_from_slice(data);
+        rdr.set_position(0);
+
+        let is_full = fragment_type == FragmentType::Full;
+        self.parser.run_fragment(is_full)?;
+
+        self.did_handle_start |= fragment_type == FragmentType::Start;
+        self.did_handle_full |= is_full;
+        Ok(())
+    }
+
+    #[inline]
+    pub fn visitor(&self) -> &V {
+        self.parser.visitor()
+    }
+
+    #[inline]
+    pub fn visitor_mut(&mut self) -> &mut V {
+        self.parser.visitor_mut()
+    }
+
+    // NOTE: following methods are public-facing api; do not use them internally
+
+    #[inline]
+    pub fn tick(&self) -> i32 {
+        self.parser.tick()
+    }
+
+    #[inline]
+    pub fn string_tables(&self) -> Option<&StringTableContainer> {
+        self.parser.string_tables()
+    }
+
+    #[inline]
+    pub fn serializers(&self) -> Option<&FlattenedSerializerContainer> {
+        self.parser.serializers()
+    }
+
+    #[inline]
+    pub fn entities(&self) -> Option<&EntityContainer> {
+        self.parser.entities()
+    }
+
+    #[inline]
+    pub fn game_event_list(&self) -> Option<&GameEventList> {
+        self.parser.game_event_list()
+    }
+}
+
+impl BroadcastParser<NopVisitor> {
+    #[inline]
+    pub fn new() -> Self {
+        Self::new_with_visitor(NopVisitor)
+    }
+}
+
+impl Default for BroadcastParser<NopVisitor> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{demofile::CmdHeader, parser::Context, protos::EDemoCommands};
+
+    #[derive(Default)]
+    struct CmdVisitor {
+        cmds: Vec<(EDemoCommands, i32)>,
+    }
+
+    impl Visitor for CmdVisitor {
+        fn on_cmd(
+            &mut self,
+            _ctx: &Context,
+            cmd_header: &CmdHeader,
+            _data: &[u8],
+        ) -> crate::Result<()> {
+            self.cmds.push((cmd_header.command, cmd_header.tick));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_push_fragment() -> crate::Result<()> {
+        let sync_tick = EDemoCommands::DemSyncTick as u8;
+        let full_packet = EDemoCommands::DemFullPacket as u8;
+        let packet = EDemoCommands::DemPacket as u8;
+
+        let mut parser = BroadcastParser::new_with_visitor(CmdVisitor::default());
+        assert!(matches!(
+            parser.push_fragment(FragmentType::Delta, &[]),
+            Err(crate::Error::Broadcast(Error::MissingStart(
+                FragmentType::Delta
+            )))
+        ));
+
+        parser.push_fragment(FragmentType::Start, &[sync_tick, 0, 0])?;
+        assert!(matches!(
+            parser.push_fragment(FragmentType::Delta, &[]),
+            Err(crate::Error::Broadcast(Error::MissingFull))
+        ));
+
+        parser.push_fragment(FragmentType::Full, &[full_packet, 10, 0])?;
+        parser.push_fragment(FragmentType::Delta, &[packet, 11, 0, packet, 12, 0])?;
+        assert_eq!(parser.tick(), 12);
+        assert_eq!(
+            parser.visitor().cmds,
+            [
+                (EDemoCommands::DemSyncTick, 0),
+                (EDemoCommands::DemFullPacket, 10),
+                (EDemoCommands::DemPacket, 11),
+                (EDemoCommands::DemPacket, 12),
+            ]
+        );
+
+        Ok(())
+    }
+}
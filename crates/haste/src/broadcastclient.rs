@@ -0,0 +1,322 @@
+use crate::{
+    broadcast::{BroadcastParser, FragmentType},
+    parser::Visitor,
+};
+use std::{
+    io::{self, Read, Write},
+    net::TcpStream,
+    time::Duration,
+};
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    // std
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    // mod
+    #[error("invalid broadcast url {0:?} (expected http://host[:port][/path])")]
+    InvalidUrl(String),
+    #[error("malformed http response")]
+    MalformedResponse,
+    #[error("unexpected http status {status} for {path}")]
+    Status { status: u16, path: String },
+    #[error("{fragment_type:?} fragment {fragment} is not available")]
+    FragmentNotFound {
+        fragment: u32,
+        fragment_type: FragmentType,
+    },
+    #[error("http response exceeds {0} bytes")]
+    OversizedResponse(u64),
+    #[error("sync response is missing {0}")]
+    MissingSyncField(&'static str),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+// NOTE: full fragments are the biggest responses; they usually take a few
+// megabytes.
+const DEFAULT_MAX_RESPONSE_SIZE: u64 = 64 * 1024 * 1024;
+
+// SyncInfo is a subset of what broadcast's /sync endpoint responds with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyncInfo {
+    pub tick: i32,
+    // fragment is the fragment that clients are supposed to join at.
+    pub fragment: u32,
+    // signup_fragment is the fragment that carries start (signon) data.
+    pub signup_fragment: u32,
+}
+
+// BroadcastClient polls a broadcast base url (the one that tv_broadcast_url
+// points to) and feeds fragments into a [`BroadcastParser`].
+//
+// NOTE: this is a minimal blocking http/1.0 client that speaks plain http
+// only; put a tls-terminating proxy in front of it if the relay is https.
+pub struct BroadcastClient {
+    // host is host[:port] as it goes into Host header.
+    host: String,
+    addr: String,
+    // path is a base path without trailing slash.
+    path: String,
+    timeout: Option<Duration>,
+    max_response_size: u64,
+    next_fragment: Option<u32>,
+}
+
+impl BroadcastClient {
+    pub fn new(base_url: &str) -> Result<Self> {
+        let rest = base_url
+            .strip_prefix("http://")
+            .ok_or_else(|| Error::InvalidUrl(base_url.to_string()))?;
+        let (host, path) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+        if host.is_empty() {
+            return Err(Error::InvalidUrl(base_url.to_string()));
+        }
+
+        let addr = if host.contains(':') {
+            host.to_string()
+        } else {
+            format!("{host}:80")
+        };
+
+        Ok(Self {
+            host: host.to_string(),
+            addr,
+            path: path.trim_end_matches('/').to_string(),
+            timeout: Some(DEFAULT_TIMEOUT),
+            max_response_size: DEFAULT_MAX_RESPONSE_SIZE,
+            next_fragment: None,
+        })
+    }
+
+    #[inline]
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout;
+    }
+
+    // set_max_response_size limits size of responses (including headers);
+    // bigger responses result in [`Error::OversizedResponse`].
+    #[inline]
+    pub fn set_max_response_size(&mut self, max_response_size: u64) {
+        self.max_response_size = max_response_size;
+    }
+
+    // next_fragment is the fragment whose delta will be requested by the next
+    // poll; is none until the client joined the broadcast.
+    #[inline]
+    pub fn next_fragment(&self) -> Option<u32> {
+        self.next_fragment
+    }
+
+    pub fn sync(&self) -> Result<SyncInfo> {
+        let body = self.get("sync")?.ok_or_else(|| Error::Status {
+            status: 404,
+            path: format!("{}/sync", self.path),
+        })?;
+        let json = String::from_utf8_lossy(&body);
+
+        let field = |key: &'static str| json_number(&json, key).ok_or(Error::MissingSyncField(key));
+        Ok(SyncInfo {
+            tick: field("tick")? as i32,
+            fragment: field("fragment")? as u32,
+            signup_fragment: field("signup_fragment")? as u32,
+        })
+    }
+
+    // fragment fetches a fragment; returns none if the fragment is not
+    // available (yet).
+    pub fn fragment(&self, fragment: u32, fragment_type: FragmentType) -> Result<Option<Vec<u8>>> {
+        self.get(&format!("{fragment}/{}", fragment_type.as_str()))
+    }
+
+    // poll joins the broadcast (if it did not yet) and pushes all deltas that
+    // are available into the parser; returns number of handled delta
+    // fragments. call it periodically (every second or so) with the same
+    // parser.
+    pub fn poll<V: Visitor>(&mut self, parser: &mut BroadcastParser<V>) -> crate::Result<usize> {
+        let mut fragment = match self.next_fragment {
+            Some(fragment) => fragment,
+            None => {
+                let sync = self.sync()?;
+                let start = self.required_fragment(sync.signup_fragment, FragmentType::Start)?;
+                parser.push_fragment(FragmentType::Start, &start)?;
+                let full = self.required_fragment(sync.fragment, FragmentType::Full)?;
+                parser.push_fragment(FragmentType::Full, &full)?;
+                self.next_fragment = Some(sync.fragment);
+                sync.fragment
+            }
+        };
+
+        let mut n = 0;
+        while let Some(delta) = self.fragment(fragment, FragmentType::Delta)? {
+            parser.push_fragment(FragmentType::Delta, &delta)?;
+            fragment += 1;
+            self.next_fragment = Some(fragment);
+            n += 1;
+        }
+        Ok(n)
+    }
+
+    fn required_fragment(&self, fragment: u32, fragment_type: FragmentType) -> Result<Vec<u8>> {
+        self.fragment(fragment, fragment_type)?
+            .ok_or(Error::FragmentNotFound {
+                fragment,
+                fragment_type,
+            })
+    }
+
+    // get sends a get request to base url + path; returns none on 404.
+    fn get(&self, path: &str) -> Result<Option<Vec<u8>>> {
+        let path = format!("{}/{}", self.path, path);
+
+        let mut stream = TcpStream::connect(&self.addr)?;
+        stream.set_read_timeout(self.timeout)?;
+        stream.set_write_timeout(self.timeout)?;
+
+        // NOTE: http/1.0 keeps things simple: there's no chunked encoding and
+        // the server closes the connection once the response is sent.
+        write!(stream, "GET {path} HTTP/1.0\r\nHost: {}\r\n\r\n", self.host)?;
+        let mut buf = Vec::new();
+        // NOTE: one extra byte tells whether the response is bigger than the
+        // limit.
+        stream
+            .take(self.max_response_size.saturating_add(1))
+            .read_to_end(&mut buf)?;
+        if buf.len() as u64 > self.max_response_size {
+            return Err(Error::OversizedResponse(self.max_response_size));
+        }
+
+        let header_end = buf
+            .windows(4)
+            .position(|window| window == b"\r\n\r\n")
+            .ok_or(Error::MalformedResponse)?;
+        let status = std::str::from_utf8(&buf[..header_end])
+            .ok()
+            .and_then(|head| head.split_whitespace().nth(1))
+            .and_then(|status| status.parse::<u16>().ok())
+            .ok_or(Error::MalformedResponse)?;
+
+        match status {
+            200 => {
+                buf.drain(..header_end + 4);
+                Ok(Some(buf))
+            }
+            404 => Ok(None),
+            status => Err(Error::Status { status, path }),
+        }
+    }
+}
+
+// json_number extracts a number of a top level key from a json object. sync
+// responses are flat, there's no need for a real json parser.
+fn json_number(json: &str, key: &str) -> Option<i64> {
+    let key = format!("\"{key}\"");
+    let rest = &json[json.find(&key)? + key.len()..];
+    let rest = rest.trim_start().strip_prefix(':')?.trim_start();
+    let end = rest
+        .find(|c: char| c != '-' && !c.is_ascii_digit())
+        .unwrap_or(rest.len());
+    rest[..end].parse().ok()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::protos::EDemoCommands;
+    use std::{
+        collections::HashMap,
+        io::{BufRead, BufReader},
+        net::TcpListener,
+        sync::{Arc, Mutex},
+        thread,
+    };
+
+    // serve imitates a broadcast relay; responds with 404 to unknown paths.
+    fn serve(routes: Arc<Mutex<HashMap<String, Vec<u8>>>>) -> io::Result<String> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let addr = listener.local_addr()?;
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else { continue };
+                // NOTE: headers must be read, otherwise closing the socket with
+                // unread data may reset the connection.
+                let mut lines = BufReader::new(&stream).lines().map_while(|line| line.ok());
+                let request_line = lines.next().unwrap_or_default();
+                lines.take_while(|line| !line.is_empty()).for_each(drop);
+                let path = request_line.split_whitespace().nth(1).unwrap_or_default();
+                let response = match routes.lock().ok().and_then(|r| r.get(path).cloned()) {
+                    Some(body) => [b"HTTP/1.0 200 OK\r\n\r\n".to_vec(), body].concat(),
+                    None => b"HTTP/1.0 404 Not Found\r\n\r\n".to_vec(),
+                };
+                let _ = stream.write_all(&response);
+            }
+        });
+        Ok(format!("http://{addr}/match"))
+    }
+
+    #[test]
+    fn test_json_number() {
+        let json = r#"{"tick":1234,"rtdelay":1.5,"fragment": 7,"signup_fragment":0,"map":"x"}"#;
+        assert_eq!(json_number(json, "tick"), Some(1234));
+        assert_eq!(json_number(json, "fragment"), Some(7));
+        assert_eq!(json_number(json, "signup_fragment"), Some(0));
+        assert_eq!(json_number(json, "map"), None);
+        assert_eq!(json_number(json, "protocol"), None);
+    }
+
+    #[test]
+    fn test_poll() -> crate::Result<()> {
+        let sync_tick = EDemoCommands::DemSyncTick as u8;
+        let full_packet = EDemoCommands::DemFullPacket as u8;
+        let packet = EDemoCommands::DemPacket as u8;
+
+        let routes = Arc::new(Mutex::new(HashMap::from([
+            (
+                "/match/sync".to_string(),
+                br#"{"tick":10,"fragment":1,"signup_fragment":0}"#.to_vec(),
+            ),
+            ("/match/0/start".to_string(), vec![sync_tick, 0, 0]),
+            ("/match/1/full".to_string(), vec![full_packet, 10, 0]),
+            ("/match/1/delta".to_string(), vec![packet, 11, 0]),
+            ("/match/2/delta".to_string(), vec![packet, 12, 0]),
+        ])));
+        let base_url = serve(routes.clone())?;
+
+        let mut client = BroadcastClient::new(&base_url)?;
+        let mut parser = BroadcastParser::new();
+        assert_eq!(client.poll(&mut parser)?, 2);
+        assert_eq!(parser.tick(), 12);
+
+        assert_eq!(client.poll(&mut parser)?, 0);
+        if let Ok(mut routes) = routes.lock() {
+            routes.insert("/match/3/delta".to_string(), vec![packet, 13, 0]);
+        }
+        assert_eq!(client.poll(&mut parser)?, 1);
+        assert_eq!(parser.tick(), 13);
+        assert_eq!(client.next_fragment(), Some(4));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_oversized_response() -> crate::Result<()> {
+        let routes = Arc::new(Mutex::new(HashMap::from([(
+            "/match/1/full".to_string(),
+            vec![0; 64],
+        )])));
+        let base_url = serve(routes)?;
+
+        let mut client = BroadcastClient::new(&base_url)?;
+        client.set_max_response_size(32);
+        assert!(matches!(
+            client.fragment(1, FragmentType::Full),
+            Err(Error::OversizedResponse(32))
+        ));
+
+        client.set_max_response_size(128);
+        assert_eq!(client.fragment(1, FragmentType::Full)?, Some(vec![0; 64]));
+
+        Ok(())
+    }
+}
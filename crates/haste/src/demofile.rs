@@ -3,7 +3,7 @@ use crate::protos::{
     CDemoFileInfo, EDemoCommands,
 };
 use dungers::varint;
-use std::io::{Read, Seek, SeekFrom};
+use std::io::{self, Read, Seek, SeekFrom};
 
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
@@ -24,6 +24,8 @@ pub enum Error {
     UnknownCmd(u32),
     #[error("expected cmd (cmd {0:?}")]
     ExpectedCmd(EDemoCommands),
+    #[error("cmd size {0} exceeds buffer size ({})", DEMO_BUFFER_SIZE)]
+    OversizedCmd(u32),
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
@@ -41,10 +43,14 @@ pub const DEMO_BUFFER_SIZE: usize = 2 * 1024 * 1024;
 // demo_file.seek(SeekFrom::Start(DEMO_HEADER_SIZE))
 pub const DEMO_HEADER_SIZE: usize = std::mem::size_of::<DemoHeader>();
 
+// NOTE: cmd header consists of 3 uvarint32s (command, tick and size), each of
+// which takes at most 5 bytes.
+pub const MAX_CMD_HEADER_SIZE: usize = 3 * 5;
+
 // #define DEMO_HEADER_ID "HL2DEMO"
 //
 // NOTE: strings in c/cpp are null terminated.
-const DEMO_HEADER_ID_SIZE: usize = 8;
+pub(crate) const DEMO_HEADER_ID_SIZE: usize = 8;
 const DEMO_HEADER_ID: [u8; DEMO_HEADER_ID_SIZE] = *b"PBDEMS2\0";
 
 // NOTE: naming is based on stuff from demofile.h of valve's demoinfo2 thing.
@@ -69,18 +75,229 @@ pub struct CmdHeader {
     pub bytes_read: usize,
 }
 
+// DemoRead is what DemoFile reads from. it is implemented for all readers and
+// for SliceReader.
+//
+// NOTE: it exists because rust does not have specialization; SliceReader needs
+// to be able to hand out borrowed cmds, generic readers need to copy.
+pub trait DemoRead {
+    fn reader(&mut self) -> impl Read + '_;
+
+    // read_cmd_data reads size bytes; buf is at least size bytes large and may
+    // be used as a storage.
+    fn read_cmd_data<'a>(&'a mut self, size: usize, buf: &'a mut [u8]) -> io::Result<&'a [u8]>;
+
+    // last_cmd_data returns what the last read_cmd_data call returned; buf must
+    // be the same one that was passed to read_cmd_data.
+    fn last_cmd_data<'a>(&'a self, size: usize, buf: &'a [u8]) -> &'a [u8];
+}
+
+// DemoSeek is implemented for all seekable readers and for SliceReader.
+pub trait DemoSeek: DemoRead {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64>;
+    fn stream_position(&mut self) -> io::Result<u64>;
+}
+
+impl<R: Read> DemoRead for R {
+    #[inline(always)]
+    fn reader(&mut self) -> impl Read + '_ {
+        self
+    }
+
+    #[inline(always)]
+    fn read_cmd_data<'a>(&'a mut self, size: usize, buf: &'a mut [u8]) -> io::Result<&'a [u8]> {
+        let buf = &mut buf[..size];
+        self.read_exact(buf)?;
+        Ok(buf)
+    }
+
+    #[inline(always)]
+    fn last_cmd_data<'a>(&'a self, size: usize, buf: &'a [u8]) -> &'a [u8] {
+        &buf[..size]
+    }
+}
+
+impl<R: Read + Seek> DemoSeek for R {
+    #[inline(always)]
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        Seek::seek(self, pos)
+    }
+
+    #[inline(always)]
+    fn stream_position(&mut self) -> io::Result<u64> {
+        Seek::stream_position(self)
+    }
+}
+
+// SliceReader allows to read demo files that are entirely in memory (e.g.
+// memory mapped files, see memmap2 crate) without copying; uncompressed cmds
+// are borrowed from the slice.
+//
+// NOTE: SliceReader intentionally does not implement Read, otherwise it'll
+// conflict with blanket DemoRead impl.
+#[derive(Debug, Clone)]
+pub struct SliceReader<'a> {
+    data: &'a [u8],
+    rest: &'a [u8],
+}
+
+impl<'a> SliceReader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data, rest: data }
+    }
+
+    #[inline]
+    pub fn position(&self) -> usize {
+        self.data.len() - self.rest.len()
+    }
+}
+
+impl<'a> DemoRead for SliceReader<'a> {
+    #[inline(always)]
+    fn reader(&mut self) -> impl Read + '_ {
+        &mut self.rest
+    }
+
+    #[inline(always)]
+    fn read_cmd_data<'b>(&'b mut self, size: usize, _buf: &'b mut [u8]) -> io::Result<&'b [u8]> {
+        if self.rest.len() < size {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof));
+        }
+        let (data, rest) = self.rest.split_at(size);
+        self.rest = rest;
+        Ok(data)
+    }
+
+    #[inline(always)]
+    fn last_cmd_data<'b>(&'b self, size: usize, _buf: &'b [u8]) -> &'b [u8] {
+        let position = self.position();
+        &self.data[position - size..position]
+    }
+}
+
+impl<'a> DemoSeek for SliceReader<'a> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let (base, offset) = match pos {
+            SeekFrom::Start(offset) => (0, offset as i64),
+            SeekFrom::Current(offset) => (self.position() as i64, offset),
+            SeekFrom::End(offset) => (self.data.len() as i64, offset),
+        };
+        let pos = base
+            .checked_add(offset)
+            .filter(|pos| *pos >= 0)
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "invalid seek to a negative or overflowing position",
+                )
+            })?;
+        // NOTE: unlike Cursor SliceReader does not allow to seek beyond the
+        // end.
+        let pos = (pos as usize).min(self.data.len());
+        self.rest = &self.data[pos..];
+        Ok(pos as u64)
+    }
+
+    #[inline(always)]
+    fn stream_position(&mut self) -> io::Result<u64> {
+        Ok(self.position() as u64)
+    }
+}
+
+pub(crate) fn check_demo_header_id(demofilestamp: [u8; DEMO_HEADER_ID_SIZE]) -> Result<()> {
+    if demofilestamp != DEMO_HEADER_ID {
+        return Err(Error::UnexpectedHeaderId {
+            want: DEMO_HEADER_ID,
+            got: demofilestamp,
+        });
+    }
+    Ok(())
+}
+
+// void ReadCmdHeader( unsigned char& cmd, int& tick, int &nPlayerSlot );
+pub(crate) fn parse_cmd_header<R: Read>(rdr: &mut R) -> Result<CmdHeader> {
+    let (command, command_ot, is_compressed) = {
+        let (c, ot) = varint::read_uvarint32(rdr)?;
+
+        const DEM_IS_COMPRESSED: u32 = EDemoCommands::DemIsCompressed as u32;
+        let is_compressed = c & DEM_IS_COMPRESSED == DEM_IS_COMPRESSED;
+
+        let command = if is_compressed {
+            c & !DEM_IS_COMPRESSED
+        } else {
+            c
+        };
+
+        (
+            EDemoCommands::from_i32(command as i32).ok_or(Error::UnknownCmd(command))?,
+            ot,
+            is_compressed,
+        )
+    };
+
+    let (tick, tick_ot) = {
+        let (t, ot) = varint::read_uvarint32(rdr)?;
+
+        // before the first tick / pre-game initialization messages
+        let tick = if t == u32::MAX { -1 } else { t as i32 };
+        (tick, ot)
+    };
+
+    let (size, size_ot) = varint::read_uvarint32(rdr)?;
+
+    Ok(CmdHeader {
+        command,
+        is_compressed,
+        tick,
+        size,
+        bytes_read: command_ot + tick_ot + size_ot,
+    })
+}
+
+// split_cmd_buf splits buf into a part that cmd data is read into and a part
+// that decompressed data is written to; malformed (or malicious) cmd headers
+// may declare sizes that do not fit.
+pub(crate) fn split_cmd_buf<'a>(
+    cmd_header: &CmdHeader,
+    buf: &'a mut [u8],
+) -> Result<(&'a mut [u8], &'a mut [u8])> {
+    let size = cmd_header.size as usize;
+    if size > buf.len() {
+        return Err(Error::OversizedCmd(cmd_header.size));
+    }
+    Ok(buf.split_at_mut(size))
+}
+
+// decompress_cmd decompresses data into buf if cmd is compressed, otherwise
+// data is returned as is.
+pub(crate) fn decompress_cmd<'a>(
+    cmd_header: &CmdHeader,
+    data: &'a [u8],
+    buf: &'a mut [u8],
+) -> Result<&'a [u8]> {
+    if cmd_header.is_compressed {
+        let decompress_len = snap::raw::decompress_len(data)?;
+        snap::raw::Decoder::new().decompress(data, buf)?;
+        // NOTE: we need to slice stuff up, because prost's decode can't
+        // determine when to stop.
+        Ok(&buf[..decompress_len])
+    } else {
+        Ok(data)
+    }
+}
+
 // NOTE: you should provide a reader that implements buffering (eg BufReader)
 // because it'll be much more efficient.
 
 #[derive(Debug)]
-pub struct DemoFile<R: Read + Seek> {
+pub struct DemoFile<R: DemoRead> {
     rdr: R,
     buf: Vec<u8>,
     demo_header: Option<DemoHeader>,
     file_info: Option<CDemoFileInfo>,
 }
 
-impl<R: Read + Seek> DemoFile<R> {
+impl<R: DemoRead> DemoFile<R> {
     // TODO: maybe read demo header in constructor and return a result. or maybe
     // document and make it clear somehow that it is required to call
     // read_demo_header upon doing anything else.
@@ -93,6 +310,26 @@ impl<R: Read + Seek> DemoFile<R> {
         }
     }
 
+    // from_fragment_reader is for broadcast fragments, they carry the same cmds
+    // as demo files but there's no demo header; it gets "synthesized" with
+    // zero offsets.
+    pub(crate) fn from_fragment_reader(rdr: R) -> Self {
+        let mut demo_file = Self::from_reader(rdr);
+        demo_file.demo_header = Some(DemoHeader {
+            demofilestamp: DEMO_HEADER_ID,
+            fileinfo_offset: 0,
+            spawngroups_offset: 0,
+        });
+        demo_file
+    }
+
+    // NOTE: get_mut exists for broadcast parser which swaps fragments in
+    // place.
+    #[inline]
+    pub(crate) fn get_mut(&mut self) -> &mut R {
+        &mut self.rdr
+    }
+
     // ----
 
     // demoheader_t* ReadDemoHeader( CDemoPlaybackParameters_t const *pPlaybackParameters );
@@ -103,20 +340,15 @@ impl<R: Read + Seek> DemoFile<R> {
         );
 
         let mut demofilestamp = [0u8; DEMO_HEADER_ID_SIZE];
-        self.rdr.read_exact(&mut demofilestamp)?;
-        if demofilestamp != DEMO_HEADER_ID {
-            return Err(Error::UnexpectedHeaderId {
-                want: DEMO_HEADER_ID,
-                got: demofilestamp,
-            });
-        }
+        self.rdr.reader().read_exact(&mut demofilestamp)?;
+        check_demo_header_id(demofilestamp)?;
 
         let mut buf = [0u8; 4];
 
-        self.rdr.read_exact(&mut buf)?;
+        self.rdr.reader().read_exact(&mut buf)?;
         let fileinfo_offset = i32::from_le_bytes(buf);
 
-        self.rdr.read_exact(&mut buf)?;
+        self.rdr.reader().read_exact(&mut buf)?;
         let spawngroups_offset = i32::from_le_bytes(buf);
 
         self.demo_header = Some(DemoHeader {
@@ -146,75 +378,104 @@ impl<R: Read + Seek> DemoFile<R> {
 
     // ----
 
-    // void ReadCmdHeader( unsigned char& cmd, int& tick, int &nPlayerSlot );
     pub fn read_cmd_header(&mut self) -> Result<CmdHeader> {
         debug_assert!(
             self.demo_header.is_some(),
             "expected demo header to have been read"
         );
 
-        let (command, command_ot, is_compressed) = {
-            let (c, ot) = varint::read_uvarint32(&mut self.rdr)?;
+        parse_cmd_header(&mut self.rdr.reader())
+    }
 
-            const DEM_IS_COMPRESSED: u32 = EDemoCommands::DemIsCompressed as u32;
-            let is_compressed = c & DEM_IS_COMPRESSED == DEM_IS_COMPRESSED;
+    // try_read_cmd_header is similar to read_cmd_header, but it returns none
+    // if the end of the stream is reached right before the cmd header. unlike
+    // is_eof it does not rely on seek.
+    pub fn try_read_cmd_header(&mut self) -> Result<Option<CmdHeader>> {
+        debug_assert!(
+            self.demo_header.is_some(),
+            "expected demo header to have been read"
+        );
 
-            let command = if is_compressed {
-                c & !DEM_IS_COMPRESSED
-            } else {
-                c
-            };
+        let mut first = [0u8; 1];
+        loop {
+            match self.rdr.reader().read(&mut first) {
+                Ok(0) => return Ok(None),
+                Ok(_) => break,
+                Err(err) if err.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(err) => return Err(Error::Io(err)),
+            }
+        }
 
-            (
-                EDemoCommands::from_i32(command as i32).ok_or(Error::UnknownCmd(command))?,
-                ot,
-                is_compressed,
-            )
-        };
+        parse_cmd_header(&mut first.as_slice().chain(self.rdr.reader())).map(Some)
+    }
 
-        let (tick, tick_ot) = {
-            let (t, ot) = varint::read_uvarint32(&mut self.rdr)?;
+    pub fn read_cmd(&mut self, cmd_header: &CmdHeader) -> Result<&[u8]> {
+        debug_assert!(
+            self.demo_header.is_some(),
+            "expected demo header to have been read"
+        );
 
-            // before the first tick / pre-game initialization messages
-            let tick = if t == u32::MAX { -1 } else { t as i32 };
-            (tick, ot)
-        };
+        let (left, right) = split_cmd_buf(cmd_header, &mut self.buf)?;
+        let data = self.rdr.read_cmd_data(cmd_header.size as usize, left)?;
+        decompress_cmd(cmd_header, data, right)
+    }
 
-        let (size, size_ot) = varint::read_uvarint32(&mut self.rdr)?;
+    // last_cmd returns the same data as the last read_cmd call did, without
+    // reading (or decompressing) it again.
+    //
+    // NOTE: it is only valid right after read_cmd, before anything else is
+    // read.
+    pub(crate) fn last_cmd(&self, cmd_header: &CmdHeader) -> Result<&[u8]> {
+        let size = cmd_header.size as usize;
+        let data = self.rdr.last_cmd_data(size, &self.buf);
+        if cmd_header.is_compressed {
+            let decompress_len = snap::raw::decompress_len(data)?;
+            Ok(&self.buf[size..size + decompress_len])
+        } else {
+            Ok(data)
+        }
+    }
 
-        Ok(CmdHeader {
-            command,
-            is_compressed,
-            tick,
-            size,
-            bytes_read: command_ot + tick_ot + size_ot,
-        })
+    // discard_cmd is a seek-less alternative to skip_cmd; it reads the cmd
+    // and throws it away.
+    pub fn discard_cmd(&mut self, cmd_header: &CmdHeader) -> Result<()> {
+        let (data, _) = split_cmd_buf(cmd_header, &mut self.buf)?;
+        self.rdr
+            .read_cmd_data(data.len(), data)
+            .map(|_| ())
+            .map_err(Error::from)
     }
+}
 
+impl<R: DemoSeek> DemoFile<R> {
     pub fn unread_cmd_header(&mut self, cmd_header: &CmdHeader) -> Result<()> {
         self.seek(SeekFrom::Current(-(cmd_header.bytes_read as i64)))
             .map(|_| ())
             .map_err(Error::from)
     }
 
-    pub fn read_cmd(&mut self, cmd_header: &CmdHeader) -> Result<&[u8]> {
-        debug_assert!(
-            self.demo_header.is_some(),
-            "expected demo header to have been read"
-        );
-
-        let (left, right) = self.buf.split_at_mut(cmd_header.size as usize);
-        self.rdr.read_exact(left)?;
-
-        if cmd_header.is_compressed {
-            let decompress_len = snap::raw::decompress_len(left)?;
-            snap::raw::Decoder::new().decompress(left, right)?;
-            // NOTE: we need to slice stuff up, because prost's decode can't
-            // determine when to stop.
-            Ok(&right[..decompress_len])
-        } else {
-            Ok(left)
+    // has_cmd_header_bytes checks (without parsing) whether bytes of a whole
+    // cmd header are available at the current position; the position is
+    // preserved.
+    pub(crate) fn has_cmd_header_bytes(&mut self) -> Result<bool> {
+        let pos = self.stream_position()?;
+
+        let mut buf = [0u8; MAX_CMD_HEADER_SIZE];
+        let mut len = 0;
+        while len < buf.len() {
+            match self.rdr.reader().read(&mut buf[len..]) {
+                Ok(0) => break,
+                Ok(n) => len += n,
+                Err(err) if err.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(err) => return Err(Error::Io(err)),
+            }
         }
+        self.seek(SeekFrom::Start(pos))?;
+
+        // NOTE: cmd header consists of 3 varints; last byte of a varint does
+        // not have the continuation bit set.
+        let varints = buf[..len].iter().filter(|b| *b & 0x80 == 0).count();
+        Ok(len == MAX_CMD_HEADER_SIZE || varints >= 3)
     }
 
     pub fn skip_cmd(&mut self, cmd_header: &CmdHeader) -> Result<()> {
@@ -338,3 +599,78 @@ impl<R: Read + Seek> DemoFile<R> {
         Ok(file_info.playback_ticks())
     }
 }
+
+// make_demo builds demo bytes for tests: demo header (with zero offsets)
+// followed by whatever f appends, see [`push_cmd`].
+#[cfg(test)]
+pub(crate) fn make_demo(f: impl FnOnce(&mut Vec<u8>)) -> Vec<u8> {
+    let mut data = DEMO_HEADER_ID.to_vec();
+    data.extend_from_slice(&[0; 8]);
+    f(&mut data);
+    data
+}
+
+// push_cmd_header appends cmd header; tick must fit into a single varint byte
+// (be less than 128).
+#[cfg(test)]
+pub(crate) fn push_cmd_header(
+    data: &mut Vec<u8>,
+    command: EDemoCommands,
+    tick: u8,
+    mut size: usize,
+) {
+    data.extend_from_slice(&[command as u8, tick]);
+    while size >= 0x80 {
+        data.push(size as u8 | 0x80);
+        size >>= 7;
+    }
+    data.push(size as u8);
+}
+
+#[cfg(test)]
+pub(crate) fn push_cmd(data: &mut Vec<u8>, command: EDemoCommands, tick: u8, cmd: &[u8]) {
+    push_cmd_header(data, command, tick, cmd.len());
+    data.extend_from_slice(cmd);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_slice_reader() -> Result<()> {
+        let data = make_demo(|data| push_cmd(data, EDemoCommands::DemSyncTick, 0, &[1, 2, 3]));
+
+        let mut demo_file = DemoFile::from_reader(SliceReader::new(&data));
+        demo_file.read_demo_header()?;
+
+        let cmd_header = demo_file.read_cmd_header()?;
+        let cmd = demo_file.read_cmd(&cmd_header)?;
+        assert_eq!(cmd, [1, 2, 3]);
+        // NOTE: uncompressed cmds must be borrowed, not copied.
+        assert_eq!(cmd.as_ptr(), data[data.len() - 3..].as_ptr());
+
+        assert!(demo_file.is_eof()?);
+        demo_file.seek(SeekFrom::Start(DEMO_HEADER_SIZE as u64))?;
+        assert_eq!(demo_file.read_cmd_header()?.size, 3);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_discard_oversized_cmd() {
+        let cmd_header = CmdHeader {
+            command: EDemoCommands::DemPacket,
+            is_compressed: false,
+            tick: 0,
+            size: DEMO_BUFFER_SIZE as u32 + 1,
+            bytes_read: 0,
+        };
+
+        let mut demo_file = DemoFile::from_reader(io::Cursor::new([0u8; 8]));
+        assert!(matches!(
+            demo_file.discard_cmd(&cmd_header),
+            Err(Error::OversizedCmd(size)) if size == cmd_header.size
+        ));
+    }
+}
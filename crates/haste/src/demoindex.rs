@@ -0,0 +1,337 @@
+use crate::{
+    demofile::{self, DemoFile, DemoSeek, DEMO_HEADER_SIZE},
+    fxhash,
+    protos::EDemoCommands,
+};
+use std::io::{Read, SeekFrom, Write};
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    // std
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    // crate
+    #[error(transparent)]
+    DemoFile(#[from] demofile::Error),
+    // mod
+    #[error("unexpected index magic (want {want:?}, got {got:?})")]
+    UnexpectedMagic { want: [u8; 8], got: [u8; 8] },
+    #[error("unsupported index version {0}")]
+    UnsupportedVersion(u32),
+    #[error("index does not belong to this demo file")]
+    Mismatch,
+    #[error("expected file header cmd")]
+    MissingFileHeader,
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+const INDEX_MAGIC: [u8; 8] = *b"HSTEIDX\0";
+const INDEX_VERSION: u32 = 2;
+
+// FullPacketEntry points at the cmd header of a DemFullPacket command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FullPacketEntry {
+    pub tick: i32,
+    pub offset: u64,
+}
+
+// Fingerprint identifies the demo file an index was built for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Fingerprint {
+    file_len: u64,
+    // file_header_hash is a hash of DemFileHeader cmd's data; the cmd contains
+    // things like server name, map name and build number.
+    file_header_hash: u64,
+}
+
+impl Fingerprint {
+    fn compute<R: DemoSeek>(demo_file: &mut DemoFile<R>) -> Result<Self> {
+        // NOTE: cmd headers can only be read after demo header.
+        demo_file.demo_header()?;
+        let backup = demo_file.stream_position()?;
+
+        let file_len = demo_file.seek(SeekFrom::End(0))?;
+
+        demo_file.seek(SeekFrom::Start(DEMO_HEADER_SIZE as u64))?;
+        let cmd_header = demo_file.read_cmd_header()?;
+        if cmd_header.command != EDemoCommands::DemFileHeader {
+            return Err(Error::MissingFileHeader);
+        }
+        let file_header_hash = fxhash::hash_bytes(demo_file.read_cmd(&cmd_header)?);
+
+        demo_file.seek(SeekFrom::Start(backup))?;
+
+        Ok(Self {
+            file_len,
+            file_header_hash,
+        })
+    }
+}
+
+// DemoIndex is a list of positions of all full packets within a demo file; it
+// allows to jump to the closest full packet instead of scanning the file from
+// the beginning.
+//
+// NOTE: building an index requires one pass over the file, but that pass only
+// reads cmd headers and skips everything else. index can be written somewhere
+// (e.g. next to the replay) and read back later, see [`Self::write_to`] and
+// [`Self::read_from`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DemoIndex {
+    fingerprint: Fingerprint,
+    // signon_end_offset is a position right after the first DemSyncTick
+    // command, everything before it is "initialization" (string tables,
+    // flattened serializers, entity classes).
+    signon_end_offset: u64,
+    full_packets: Vec<FullPacketEntry>,
+}
+
+impl DemoIndex {
+    pub fn build<R: DemoSeek>(demo_file: &mut DemoFile<R>) -> Result<Self> {
+        let fingerprint = Fingerprint::compute(demo_file)?;
+
+        let backup = demo_file.stream_position()?;
+        demo_file.seek(SeekFrom::Start(DEMO_HEADER_SIZE as u64))?;
+
+        let mut offset = DEMO_HEADER_SIZE as u64;
+        let mut signon_end_offset = None;
+        let mut full_packets = Vec::new();
+        loop {
+            let cmd_header = match demo_file.read_cmd_header() {
+                Ok(cmd_header) => cmd_header,
+                Err(err) => {
+                    if demo_file.is_eof().unwrap_or_default() {
+                        break;
+                    }
+                    return Err(Error::from(err));
+                }
+            };
+            let next_offset = offset + cmd_header.bytes_read as u64 + cmd_header.size as u64;
+
+            match cmd_header.command {
+                EDemoCommands::DemSyncTick if signon_end_offset.is_none() => {
+                    signon_end_offset = Some(next_offset);
+                }
+                EDemoCommands::DemFullPacket => {
+                    full_packets.push(FullPacketEntry {
+                        tick: cmd_header.tick,
+                        offset,
+                    });
+                }
+                EDemoCommands::DemStop => break,
+                _ => {}
+            }
+
+            demo_file.skip_cmd(&cmd_header)?;
+            offset = next_offset;
+        }
+
+        demo_file.seek(SeekFrom::Start(backup))?;
+
+        Ok(Self {
+            fingerprint,
+            signon_end_offset: signon_end_offset.unwrap_or(offset),
+            full_packets,
+        })
+    }
+
+    // ----
+
+    pub fn write_to<W: Write>(&self, w: &mut W) -> Result<()> {
+        w.write_all(&INDEX_MAGIC)?;
+        w.write_all(&INDEX_VERSION.to_le_bytes())?;
+        w.write_all(&self.fingerprint.file_len.to_le_bytes())?;
+        w.write_all(&self.fingerprint.file_header_hash.to_le_bytes())?;
+        w.write_all(&self.signon_end_offset.to_le_bytes())?;
+        w.write_all(&(self.full_packets.len() as u32).to_le_bytes())?;
+        for full_packet in &self.full_packets {
+            w.write_all(&full_packet.tick.to_le_bytes())?;
+            w.write_all(&full_packet.offset.to_le_bytes())?;
+        }
+        Ok(())
+    }
+
+    pub fn read_from<R: Read>(r: &mut R) -> Result<Self> {
+        let mut magic = [0u8; 8];
+        r.read_exact(&mut magic)?;
+        if magic != INDEX_MAGIC {
+            return Err(Error::UnexpectedMagic {
+                want: INDEX_MAGIC,
+                got: magic,
+            });
+        }
+
+        let mut buf4 = [0u8; 4];
+        let mut buf8 = [0u8; 8];
+
+        r.read_exact(&mut buf4)?;
+        let version = u32::from_le_bytes(buf4);
+        if version != INDEX_VERSION {
+            return Err(Error::UnsupportedVersion(version));
+        }
+
+        r.read_exact(&mut buf8)?;
+        let file_len = u64::from_le_bytes(buf8);
+        r.read_exact(&mut buf8)?;
+        let file_header_hash = u64::from_le_bytes(buf8);
+
+        r.read_exact(&mut buf8)?;
+        let signon_end_offset = u64::from_le_bytes(buf8);
+
+        r.read_exact(&mut buf4)?;
+        let len = u32::from_le_bytes(buf4) as usize;
+
+        let mut full_packets = Vec::with_capacity(len);
+        for _ in 0..len {
+            r.read_exact(&mut buf4)?;
+            let tick = i32::from_le_bytes(buf4);
+            r.read_exact(&mut buf8)?;
+            let offset = u64::from_le_bytes(buf8);
+            full_packets.push(FullPacketEntry { tick, offset });
+        }
+
+        Ok(Self {
+            fingerprint: Fingerprint {
+                file_len,
+                file_header_hash,
+            },
+            signon_end_offset,
+            full_packets,
+        })
+    }
+
+    // ----
+
+    // validate checks whether the index was built for the given demo file;
+    // indexes of files that were modified (or that are still being written)
+    // are rejected.
+    pub fn validate<R: DemoSeek>(&self, demo_file: &mut DemoFile<R>) -> Result<()> {
+        if Fingerprint::compute(demo_file)? != self.fingerprint {
+            return Err(Error::Mismatch);
+        }
+        Ok(())
+    }
+
+    #[inline]
+    pub fn signon_end_offset(&self) -> u64 {
+        self.signon_end_offset
+    }
+
+    #[inline]
+    pub fn full_packets(&self) -> &[FullPacketEntry] {
+        &self.full_packets
+    }
+
+    // find_full_packet returns the last full packet whose tick is less than or
+    // equal to target tick.
+    pub fn find_full_packet(&self, target_tick: i32) -> Option<&FullPacketEntry> {
+        let n = self
+            .full_packets
+            .partition_point(|full_packet| full_packet.tick <= target_tick);
+        if n == 0 {
+            None
+        } else {
+            self.full_packets.get(n - 1)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn make_index() -> DemoIndex {
+        DemoIndex {
+            fingerprint: Fingerprint {
+                file_len: 200000,
+                file_header_hash: 1337,
+            },
+            signon_end_offset: 4096,
+            full_packets: vec![
+                FullPacketEntry {
+                    tick: 0,
+                    offset: 5000,
+                },
+                FullPacketEntry {
+                    tick: 1800,
+                    offset: 90000,
+                },
+                FullPacketEntry {
+                    tick: 3600,
+                    offset: 180000,
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_write_read_roundtrip() -> Result<()> {
+        let index = make_index();
+        let mut buf = Vec::new();
+        index.write_to(&mut buf)?;
+        let got = DemoIndex::read_from(&mut buf.as_slice())?;
+        assert_eq!(index, got);
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_full_packet() {
+        let index = make_index();
+        assert_eq!(index.find_full_packet(-1), None);
+        assert_eq!(index.find_full_packet(0).map(|fp| fp.tick), Some(0));
+        assert_eq!(index.find_full_packet(1799).map(|fp| fp.tick), Some(0));
+        assert_eq!(index.find_full_packet(1800).map(|fp| fp.tick), Some(1800));
+        assert_eq!(index.find_full_packet(99999).map(|fp| fp.tick), Some(3600));
+    }
+
+    fn make_demo(server_name: u8) -> Vec<u8> {
+        demofile::make_demo(|data| {
+            for (command, tick, cmd) in [
+                (EDemoCommands::DemFileHeader, 0, vec![server_name]),
+                (EDemoCommands::DemSyncTick, 0, vec![]),
+                (EDemoCommands::DemFullPacket, 0, vec![1, 2]),
+                (EDemoCommands::DemPacket, 1, vec![3]),
+                (EDemoCommands::DemStop, 1, vec![]),
+            ] {
+                demofile::push_cmd(data, command, tick, &cmd);
+            }
+        })
+    }
+
+    #[test]
+    fn test_build_and_validate() -> Result<()> {
+        let mut demo_file = DemoFile::from_reader(std::io::Cursor::new(make_demo(1)));
+        demo_file.read_demo_header()?;
+
+        let index = DemoIndex::build(&mut demo_file)?;
+        // file header + sync tick
+        assert_eq!(index.signon_end_offset(), DEMO_HEADER_SIZE as u64 + 4 + 3);
+        assert_eq!(
+            index.full_packets(),
+            [FullPacketEntry {
+                tick: 0,
+                offset: DEMO_HEADER_SIZE as u64 + 4 + 3,
+            }]
+        );
+        index.validate(&mut demo_file)?;
+
+        // same length, different file header
+        let mut demo_file = DemoFile::from_reader(std::io::Cursor::new(make_demo(2)));
+        assert!(matches!(
+            index.validate(&mut demo_file),
+            Err(Error::Mismatch)
+        ));
+
+        // same file header, different length
+        let mut data = make_demo(1);
+        data.extend_from_slice(&[EDemoCommands::DemStop as u8, 1, 0]);
+        let mut demo_file = DemoFile::from_reader(std::io::Cursor::new(data));
+        assert!(matches!(
+            index.validate(&mut demo_file),
+            Err(Error::Mismatch)
+        ));
+
+        Ok(())
+    }
+}
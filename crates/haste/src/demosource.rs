@@ -0,0 +1,229 @@
+use crate::parser::{NopVisitor, Parser, Visitor};
+use std::{
+    fs::File,
+    io::{self, BufReader, Cursor, Read, Seek, SeekFrom},
+    path::Path,
+};
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    // std
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    // mod
+    #[error("unknown demo file format (magic {0:?})")]
+    UnknownFormat([u8; 8]),
+    #[error("{0:?} compression is not enabled, see haste's features")]
+    CompressionNotEnabled(Compression),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+const MAGIC_SIZE: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    Bzip2,
+    Zstd,
+    Gzip,
+}
+
+impl Compression {
+    // detect sniffs compression from the first bytes of the file.
+    pub fn detect(magic: &[u8]) -> Option<Self> {
+        if magic.starts_with(b"PBDEMS2\0") {
+            Some(Self::None)
+        } else if magic.starts_with(b"BZh") {
+            Some(Self::Bzip2)
+        } else if magic.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+            Some(Self::Zstd)
+        } else if magic.starts_with(&[0x1f, 0x8b]) {
+            Some(Self::Gzip)
+        } else {
+            None
+        }
+    }
+}
+
+// DemoSource is what [`open`] gives to the parser. uncompressed files are read
+// directly; compressed files are fully decompressed into memory (before
+// anything is parsed) because parser needs to be able to seek.
+//
+// NOTE: to parse compressed replays without buffering them entirely wrap the
+// decompressor into a parser on your own and use Parser::run_stream.
+pub enum DemoSource {
+    File(BufReader<File>),
+    Memory(Cursor<Vec<u8>>),
+}
+
+impl DemoSource {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let mut file = File::open(path)?;
+
+        let mut magic = [0u8; MAGIC_SIZE];
+        let n = read_up_to(&mut file, &mut magic)?;
+        file.seek(SeekFrom::Start(0))?;
+
+        let compression = Compression::detect(&magic[..n]).ok_or(Error::UnknownFormat(magic))?;
+        match compression {
+            Compression::None => Ok(Self::File(BufReader::new(file))),
+            #[cfg(feature = "bzip2")]
+            Compression::Bzip2 => Self::decompress(bzip2::read::MultiBzDecoder::new(file)),
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => Self::decompress(zstd::stream::read::Decoder::new(file)?),
+            #[cfg(feature = "gzip")]
+            Compression::Gzip => Self::decompress(flate2::read::MultiGzDecoder::new(file)),
+            #[allow(unreachable_patterns)]
+            compression => Err(Error::CompressionNotEnabled(compression)),
+        }
+    }
+
+    #[cfg(any(feature = "bzip2", feature = "zstd", feature = "gzip"))]
+    fn decompress<R: Read>(mut rdr: R) -> Result<Self> {
+        let mut buf = Vec::new();
+        rdr.read_to_end(&mut buf)?;
+        Ok(Self::Memory(Cursor::new(buf)))
+    }
+}
+
+// read_up_to is like read_exact, but it does not fail if there's less data then
+// buf can fit.
+fn read_up_to<R: Read>(rdr: &mut R, buf: &mut [u8]) -> io::Result<usize> {
+    let mut n = 0;
+    while n < buf.len() {
+        match rdr.read(&mut buf[n..]) {
+            Ok(0) => break,
+            Ok(m) => n += m,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(n)
+}
+
+impl Read for DemoSource {
+    #[inline]
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Self::File(rdr) => rdr.read(buf),
+            Self::Memory(rdr) => rdr.read(buf),
+        }
+    }
+}
+
+impl Seek for DemoSource {
+    #[inline]
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        match self {
+            Self::File(rdr) => rdr.seek(pos),
+            Self::Memory(rdr) => rdr.seek(pos),
+        }
+    }
+
+    #[inline]
+    fn stream_position(&mut self) -> io::Result<u64> {
+        match self {
+            Self::File(rdr) => rdr.stream_position(),
+            Self::Memory(rdr) => rdr.stream_position(),
+        }
+    }
+}
+
+// open opens a demo file that may be compressed with bzip2 (which is how
+// valve's replay cdn serves them), zstd or gzip; compression is detected by
+// magic bytes.
+//
+// NOTE: compressed files are buffered into memory entirely, see [`DemoSource`].
+pub fn open<P: AsRef<Path>>(path: P) -> crate::Result<Parser<DemoSource, NopVisitor>> {
+    Parser::from_reader(DemoSource::open(path)?)
+}
+
+pub fn open_with_visitor<P: AsRef<Path>, V: Visitor>(
+    path: P,
+    visitor: V,
+) -> crate::Result<Parser<DemoSource, V>> {
+    Parser::from_reader_with_visitor(DemoSource::open(path)?, visitor)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(
+            Compression::detect(b"PBDEMS2\0\x00\x00"),
+            Some(Compression::None)
+        );
+        assert_eq!(Compression::detect(b"BZh91AY&"), Some(Compression::Bzip2));
+        assert_eq!(
+            Compression::detect(&[0x28, 0xb5, 0x2f, 0xfd, 0x04]),
+            Some(Compression::Zstd)
+        );
+        assert_eq!(
+            Compression::detect(&[0x1f, 0x8b, 0x08]),
+            Some(Compression::Gzip)
+        );
+        assert_eq!(Compression::detect(b"HL2DEMO\0"), None);
+        assert_eq!(Compression::detect(b""), None);
+    }
+
+    #[cfg(any(feature = "bzip2", feature = "zstd", feature = "gzip"))]
+    fn test_open_compressed(
+        name: &str,
+        compress: impl FnOnce(&[u8]) -> io::Result<Vec<u8>>,
+    ) -> std::result::Result<(), Box<dyn std::error::Error>> {
+        use crate::{
+            demofile::{make_demo, push_cmd, DEMO_HEADER_ID_SIZE},
+            protos::EDemoCommands,
+        };
+
+        let mut data = make_demo(|data| push_cmd(data, EDemoCommands::DemStop, 0, &[]));
+        data[DEMO_HEADER_ID_SIZE..DEMO_HEADER_ID_SIZE + 4].copy_from_slice(&42i32.to_le_bytes());
+
+        let path =
+            std::env::temp_dir().join(format!("haste-test-open-{}-{name}.dem", std::process::id()));
+        std::fs::write(&path, compress(&data)?)?;
+        let result = (|| -> crate::Result<_> {
+            let mut parser = open(&path)?;
+            let fileinfo_offset = parser.demo_header().fileinfo_offset;
+            parser.run_to_end()?;
+            Ok(fileinfo_offset)
+        })();
+        std::fs::remove_file(&path)?;
+
+        assert_eq!(result?, 42);
+        Ok(())
+    }
+
+    #[cfg(feature = "bzip2")]
+    #[test]
+    fn test_open_bzip2() -> std::result::Result<(), Box<dyn std::error::Error>> {
+        test_open_compressed("bzip2", |data| {
+            use std::io::Write;
+            let mut encoder =
+                bzip2::write::BzEncoder::new(Vec::new(), bzip2::Compression::default());
+            encoder.write_all(data)?;
+            encoder.finish()
+        })
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn test_open_gzip() -> std::result::Result<(), Box<dyn std::error::Error>> {
+        test_open_compressed("gzip", |data| {
+            use std::io::Write;
+            let mut encoder =
+                flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(data)?;
+            encoder.finish()
+        })
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn test_open_zstd() -> std::result::Result<(), Box<dyn std::error::Error>> {
+        test_open_compressed("zstd", |data| zstd::stream::encode_all(data, 0))
+    }
+}
@@ -3,16 +3,17 @@ use crate::{
     entityclasses::EntityClasses,
     fielddecoder,
     fieldpath::{self, FieldPath},
-    fieldvalue::FieldValue,
+    fieldvalue::{self, FieldValue},
     flattenedserializers::{
         FlattenedSerializer, FlattenedSerializerContainer, FlattenedSerializerField,
     },
     fxhash,
     instancebaseline::InstanceBaseline,
+    Shared,
 };
 use hashbrown::{hash_map::Entry, HashMap};
 use nohash::NoHashHasher;
-use std::{hash::BuildHasherDefault, rc::Rc};
+use std::hash::BuildHasherDefault;
 
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
@@ -23,6 +24,25 @@ pub enum Error {
     FieldPath(#[from] fieldpath::Error),
     #[error(transparent)]
     FieldDecoder(#[from] fielddecoder::Error),
+    #[error(transparent)]
+    FieldValue(#[from] fieldvalue::Error),
+    // mod
+    #[error("field {0} does not exist")]
+    MissingField(u64),
+    #[error("unexpected serializer (want {want}, got {got})")]
+    UnexpectedSerializer { want: &'static str, got: u64 },
+    // NOTE: following errors can only be returned with safe feature; they
+    // indicate that replay is corrupted.
+    #[error("unknown class id {0}")]
+    UnknownClassId(i32),
+    #[error("unknown serializer {0}")]
+    UnknownSerializer(u64),
+    #[error("missing instance baseline for class id {0}")]
+    MissingBaseline(i32),
+    #[error("entity does not exist")]
+    UnknownEntity,
+    #[error("field path does not point to a field")]
+    InvalidFieldPath,
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
@@ -58,7 +78,7 @@ pub(crate) fn parse_delta_header(br: &mut BitReader) -> Result<usize> {
 
 // Used to classify entity update types in DeltaPacketEntities.
 // csgo src: engine/ents_shared.h
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum UpdateType {
     EnterPVS = 0, // Entity came back into pvs, create new entity if one doesn't exist
     LeavePVS,     // Entity left pvs
@@ -77,6 +97,46 @@ pub(crate) fn determine_update_type(update_flags: usize) -> UpdateType {
     }
 }
 
+// entity handles. see public/const.h (source 1) / public/entityhandle.h.
+//
+// NOTE: entity serial numbers are 17 bits wide, but networked handles carry
+// only lower 10 bits of it.
+pub const MAX_EDICT_BITS: u32 = 14;
+pub const NUM_SERIAL_NUM_BITS: u32 = 17;
+pub const NUM_NETWORKED_EHANDLE_SERIAL_NUMBER_BITS: u32 = 10;
+pub const NUM_NETWORKED_EHANDLE_BITS: u32 =
+    MAX_EDICT_BITS + NUM_NETWORKED_EHANDLE_SERIAL_NUMBER_BITS;
+pub const INVALID_NETWORKED_EHANDLE_VALUE: u32 = (1 << NUM_NETWORKED_EHANDLE_BITS) - 1;
+
+const ENT_ENTRY_MASK: u32 = (1 << MAX_EDICT_BITS) - 1;
+const NETWORKED_EHANDLE_SERIAL_MASK: u32 = (1 << NUM_NETWORKED_EHANDLE_SERIAL_NUMBER_BITS) - 1;
+
+#[inline]
+pub const fn handle_to_index(handle: u32) -> i32 {
+    (handle & ENT_ENTRY_MASK) as i32
+}
+
+#[inline]
+pub const fn handle_to_serial(handle: u32) -> u32 {
+    (handle >> MAX_EDICT_BITS) & NETWORKED_EHANDLE_SERIAL_MASK
+}
+
+#[inline]
+pub const fn make_handle(index: i32, serial: u32) -> u32 {
+    ((serial & NETWORKED_EHANDLE_SERIAL_MASK) << MAX_EDICT_BITS) | (index as u32 & ENT_ENTRY_MASK)
+}
+
+// FieldChange describes a single field that was written by the most recent
+// create / update of an entity.
+#[derive(Debug, Clone)]
+pub struct FieldChange {
+    pub key: u64,
+    // NOTE: prev_value is None if field did not have a value before. for
+    // entities that just entered pvs previous values are values from instance
+    // baseline.
+    pub prev_value: Option<FieldValue>,
+}
+
 #[derive(Debug, Clone)]
 struct EntityField {
     #[cfg(feature = "preserve-metadata")]
@@ -88,12 +148,13 @@ struct EntityField {
 #[derive(Debug, Clone)]
 pub struct Entity {
     index: i32,
+    serial: u32,
     fields: HashMap<u64, EntityField, BuildHasherDefault<NoHashHasher<u64>>>,
-    serializer: Rc<FlattenedSerializer>,
+    serializer: Shared<FlattenedSerializer>,
 }
 
 impl Entity {
-    fn parse(&mut self, br: &mut BitReader) -> Result<()> {
+    fn parse(&mut self, br: &mut BitReader, changes: &mut Vec<FieldChange>) -> Result<()> {
         // eprintln!("-- {:?}", self.serializer.serializer_name);
 
         fieldpath::FIELD_PATHS.with(|fps| unsafe {
@@ -105,23 +166,58 @@ impl Entity {
                 // NOTE: this loop performes much better then the unrolled
                 // version of it, probably because a bunch of ifs cause a bunch
                 // of branch misses and branch missles are disasterous.
+                #[cfg(not(feature = "safe"))]
                 let mut field = self.serializer.get_child_unchecked(fp.get_unchecked(0));
+                #[cfg(feature = "safe")]
+                let mut field = fp
+                    .get(0)
+                    .and_then(|i| self.serializer.get_child(i))
+                    .ok_or(Error::InvalidFieldPath)?;
                 // NOTE: field.var_name.hash is a "seed" for field_key_hash.
                 let mut field_key = field.var_name.hash;
                 for i in 1..=fp.last() {
+                    #[cfg(not(feature = "safe"))]
+                    let index = fp.get_unchecked(i);
+                    #[cfg(feature = "safe")]
+                    let index = fp.get(i).ok_or(Error::InvalidFieldPath)?;
+
                     if field.is_dynamic_array() {
-                        field = field.get_child_unchecked(0);
+                        #[cfg(not(feature = "safe"))]
+                        {
+                            field = field.get_child_unchecked(0);
+                        }
+                        #[cfg(feature = "safe")]
+                        {
+                            field = field.get_child(0).ok_or(Error::InvalidFieldPath)?;
+                        }
                         // NOTE: it's sort of weird to hash index, yup. but it simplifies things
                         // when "user" builds a key that has numbers / it makes it so that there's
                         // no need to check whether part of a key needs to be hashed or not - just
                         // hash all parts.
                         field_key = fxhash::add_u64_to_hash(
                             field_key,
-                            fxhash::add_u64_to_hash(0, fp.get_unchecked(i) as u64),
+                            fxhash::add_u64_to_hash(0, index as u64),
                         );
                     } else {
-                        field = field.get_child_unchecked(fp.get_unchecked(i));
-                        field_key = fxhash::add_u64_to_hash(field_key, field.var_name.hash);
+                        // NOTE: items of fixed arrays have the same var name as
+                        // the array itself; hash the index to tell them apart.
+                        let is_fixed_array = field.is_fixed_array();
+                        #[cfg(not(feature = "safe"))]
+                        {
+                            field = field.get_child_unchecked(index);
+                        }
+                        #[cfg(feature = "safe")]
+                        {
+                            field = field.get_child(index).ok_or(Error::InvalidFieldPath)?;
+                        }
+                        field_key = fxhash::add_u64_to_hash(
+                            field_key,
+                            if is_fixed_array {
+                                fxhash::add_u64_to_hash(0, index as u64)
+                            } else {
+                                field.var_name.hash
+                            },
+                        );
                     };
                 }
 
@@ -141,7 +237,7 @@ impl Entity {
                 //   point.
                 field.metadata.decoder.decode(br).map(|field_value| {
                     // eprintln!(" -> {:?}", &field_value);
-                    self.fields.insert(
+                    let prev = self.fields.insert(
                         field_key,
                         EntityField {
                             #[cfg(feature = "preserve-metadata")]
@@ -149,6 +245,12 @@ impl Entity {
                             value: field_value,
                         },
                     );
+                    // NOTE: previous value would've been dropped anyway, moving
+                    // it into changes costs next to nothing.
+                    changes.push(FieldChange {
+                        key: field_key,
+                        prev_value: prev.map(|ef| ef.value),
+                    });
                 })?;
             }
 
@@ -171,12 +273,74 @@ impl Entity {
         self.fields.get(key).map(|ef| &ef.value)
     }
 
+    // get is a typed variant of [`Self::get_value`]; it fails with
+    // [`Error::MissingField`] if field does not exist, or with
+    // [`Error::FieldValue`] if value can't be (losslessly) converted into T.
+    #[inline]
+    pub fn get<'a, T>(&'a self, key: &u64) -> Result<T>
+    where
+        T: TryFrom<&'a FieldValue, Error = fieldvalue::Error>,
+    {
+        self.get_value(key)
+            .ok_or(Error::MissingField(*key))
+            .and_then(|value| T::try_from(value).map_err(Error::from))
+    }
+
+    // get_array collects items of a fixed array (e.g. `int32[4]`) whose key is
+    // key; see [`make_item_key`].
+    pub fn get_array<'a, T, const N: usize>(&'a self, key: &u64) -> Result<[T; N]>
+    where
+        T: TryFrom<&'a FieldValue, Error = fieldvalue::Error>,
+    {
+        let mut items = Vec::with_capacity(N);
+        for index in 0..N {
+            items.push(self.get(&make_item_key(*key, index))?);
+        }
+        // NOTE: this can't fail, there's exactly N items.
+        items.try_into().map_err(|_| Error::MissingField(*key))
+    }
+
+    // get_vec collects items of a dynamic array (e.g. `CNetworkUtlVectorBase<
+    // int32 >`) whose key is key; the value of the array field itself is its
+    // length.
+    pub fn get_vec<'a, T>(&'a self, key: &u64) -> Result<Vec<T>>
+    where
+        T: TryFrom<&'a FieldValue, Error = fieldvalue::Error>,
+    {
+        let len = self.get::<u64>(key)?;
+        (0..len as usize)
+            .map(|index| self.get(&make_item_key(*key, index)))
+            .collect()
+    }
+
     #[cfg(feature = "preserve-metadata")]
     #[inline]
     pub fn get_path(&self, key: &u64) -> Option<&FieldPath> {
         self.fields.get(key).map(|ef| &ef.path)
     }
 
+    // get_name returns dotted path of the field (e.g.
+    // "m_vecStatViewerModifierValues.0.m_flValue"); it is the reverse of
+    // [`FlattenedSerializer::resolve_field_key`].
+    //
+    // NOTE: items of dynamic arrays are named only up to current length of the
+    // array.
+    pub fn get_name(&self, key: &u64) -> Option<String> {
+        self.serializer
+            .field_names()
+            .get(*key, |array_key| self.get::<u64>(&array_key).ok())
+    }
+
+    // get_by_path looks up field by its dotted path (e.g.
+    // "m_vecStatViewerModifierValues.0.m_flValue"); see
+    // [`FlattenedSerializer::resolve_field_key`].
+    #[inline]
+    pub fn get_by_path(&self, path: &str) -> Option<&FieldValue> {
+        self.serializer
+            .resolve_field_key(path)
+            .and_then(|key| self.get_value(&key))
+    }
+
     #[inline]
     pub fn get_serializer(&self) -> &FlattenedSerializer {
         self.serializer.as_ref()
@@ -194,13 +358,81 @@ impl Entity {
     pub fn index(&self) -> i32 {
         self.index
     }
+
+    #[inline]
+    pub fn serial(&self) -> u32 {
+        self.serial
+    }
+
+    // handle returns networked handle (as in CHandle / EHANDLE fields) of the
+    // entity.
+    #[inline]
+    pub fn handle(&self) -> u32 {
+        make_handle(self.index, self.serial)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Entity {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        let mut state = serializer.serialize_struct("Entity", 3)?;
+        state.serialize_field("index", &self.index)?;
+        #[cfg(feature = "preserve-metadata")]
+        state.serialize_field("serializer", &self.serializer.serializer_name.str)?;
+        #[cfg(not(feature = "preserve-metadata"))]
+        state.serialize_field("serializer", &self.serializer.serializer_name.hash)?;
+        state.serialize_field("fields", &SerializeEntityFields(self))?;
+        state.end()
+    }
+}
+
+// NOTE: fields are serialized as a map of dotted names (e.g.
+// "m_vecStatViewerModifierValues.0.m_flValue") to values; field keys are used
+// for fields that can't be named (see [`Entity::get_name`]).
+#[cfg(feature = "serde")]
+struct SerializeEntityFields<'a>(&'a Entity);
+
+#[cfg(feature = "serde")]
+impl<'a> serde::Serialize for SerializeEntityFields<'a> {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        use serde::ser::SerializeMap;
+
+        let entity = self.0;
+        let mut state = serializer.serialize_map(Some(entity.fields.len()))?;
+        for (key, ef) in entity.fields.iter() {
+            // NOTE: fields are named the same way the serializer is: by names
+            // with preserve-metadata, by hashes otherwise.
+            #[cfg(feature = "preserve-metadata")]
+            state.serialize_entry(
+                &entity.get_name(key).unwrap_or_else(|| key.to_string()),
+                &ef.value,
+            )?;
+            #[cfg(not(feature = "preserve-metadata"))]
+            state.serialize_entry(key, &ef.value)?;
+        }
+        state.end()
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct EntityContainer {
     // NOTE: hashbrown hashmap with no hash performs better then Vec.
     entities: HashMap<i32, Entity, BuildHasherDefault<NoHashHasher<i32>>>,
     baseline_entities: HashMap<i32, Entity, BuildHasherDefault<NoHashHasher<i32>>>,
+    // NOTE: changes is reused between creates / updates to avoid allocations;
+    // it contains changes of the most recently created / updated entity.
+    changes: Vec<FieldChange>,
+    // class_index maps serializer name hash to (sorted) indices of entities
+    // that are using that serializer.
+    class_index: HashMap<u64, Vec<i32>, BuildHasherDefault<NoHashHasher<u64>>>,
 }
 
 impl EntityContainer {
@@ -213,6 +445,23 @@ impl EntityContainer {
                 1024,
                 BuildHasherDefault::default(),
             ),
+            changes: Vec::with_capacity(1024),
+            class_index: HashMap::with_capacity_and_hasher(1024, BuildHasherDefault::default()),
+        }
+    }
+
+    fn add_to_class_index(&mut self, serializer_name_hash: u64, index: i32) {
+        let indices = self.class_index.entry(serializer_name_hash).or_default();
+        if let Err(i) = indices.binary_search(&index) {
+            indices.insert(i, index);
+        }
+    }
+
+    fn remove_from_class_index(&mut self, serializer_name_hash: u64, index: i32) {
+        if let Some(indices) = self.class_index.get_mut(&serializer_name_hash) {
+            if let Ok(i) = indices.binary_search(&index) {
+                indices.remove(i);
+            }
         }
     }
 
@@ -225,47 +474,81 @@ impl EntityContainer {
         serializers: &FlattenedSerializerContainer,
     ) -> Result<&Entity> {
         let class_id = br.read_ubitlong(entity_classes.bits)? as i32;
-        let _serial = br.read_ubitlong(17)?;
+        let serial = br.read_ubitlong(NUM_SERIAL_NUM_BITS as usize)?;
         let _unknown = br.read_uvarint32()?;
 
-        let class_info = unsafe { entity_classes.by_id_unckecked(class_id) };
-        let serializer =
-            unsafe { serializers.by_name_hash_unckecked(class_info.network_name_hash) };
+        #[cfg(not(feature = "safe"))]
+        let serializer = unsafe {
+            let class_info = entity_classes.by_id_unckecked(class_id);
+            serializers.by_name_hash_unckecked(class_info.network_name_hash)
+        };
+        #[cfg(feature = "safe")]
+        let serializer = {
+            let class_info = entity_classes
+                .by_id(class_id)
+                .ok_or(Error::UnknownClassId(class_id))?;
+            serializers
+                .by_name_hash(class_info.network_name_hash)
+                .ok_or(Error::UnknownSerializer(class_info.network_name_hash))?
+        };
 
         let mut entity = match self.baseline_entities.entry(class_id) {
             Entry::Occupied(entry) => entry.get().clone(),
             Entry::Vacant(e) => {
                 let mut entity = Entity {
                     index,
+                    serial,
                     fields: HashMap::with_capacity_and_hasher(
                         serializer.fields.len(),
                         BuildHasherDefault::default(),
                     ),
                     serializer,
                 };
+                #[cfg(not(feature = "safe"))]
                 let baseline_data = unsafe { instance_baseline.by_id_unchecked(class_id) };
+                #[cfg(feature = "safe")]
+                let baseline_data = instance_baseline
+                    .by_id(class_id)
+                    .ok_or(Error::MissingBaseline(class_id))?;
                 let mut baseline_br = BitReader::new(baseline_data.as_ref());
-                entity.parse(&mut baseline_br)?;
+                entity.parse(&mut baseline_br, &mut self.changes)?;
                 e.insert(entity).clone()
             }
         };
 
-        entity.parse(br)?;
+        // NOTE: baseline entities are shared between all entities of the same
+        // class, index and serial are the ones of the entity that was created
+        // first.
+        entity.index = index;
+        entity.serial = serial;
 
-        self.entities.insert(index, entity);
+        // NOTE: baseline is not a change, it is a starting point.
+        self.changes.clear();
+        entity.parse(br, &mut self.changes)?;
+
+        let serializer_name_hash = entity.serializer.serializer_name.hash;
+        if let Some(prev) = self.entities.insert(index, entity) {
+            self.remove_from_class_index(prev.serializer.serializer_name.hash, index);
+        }
+        self.add_to_class_index(serializer_name_hash, index);
         // SAFETY: the entity was just inserted ^, it's safe.
         Ok(unsafe { self.entities.get(&index).unwrap_unchecked() })
     }
 
     // SAFETY: if it's being deleted menas that it was created, riiight? but
     // there's a risk (that only should exist if replay is corrupted).
+    #[cfg(not(feature = "safe"))]
     #[inline]
     pub(crate) unsafe fn handle_delete_unchecked(&mut self, index: i32) -> Entity {
-        unsafe { self.entities.remove(&(index)).unwrap_unchecked() }
+        self.changes.clear();
+        let entity = unsafe { self.entities.remove(&(index)).unwrap_unchecked() };
+        self.remove_from_class_index(entity.serializer.serializer_name.hash, index);
+        entity
     }
 
     // SAFETY: if entity was ever created, and not deleted, it can be updated!
     // but there's a risk (that only should exist if replay is corrupted).
+    #[cfg(not(feature = "safe"))]
     #[inline]
     pub(crate) unsafe fn handle_update_unchecked(
         &mut self,
@@ -273,10 +556,38 @@ impl EntityContainer {
         br: &mut BitReader,
     ) -> Result<&Entity> {
         let entity = unsafe { self.entities.get_mut(&index).unwrap_unchecked() };
-        entity.parse(br)?;
+        self.changes.clear();
+        entity.parse(br, &mut self.changes)?;
+        Ok(entity)
+    }
+
+    // handle_delete is a checked variant of [`Self::handle_delete_unchecked`].
+    #[cfg(feature = "safe")]
+    #[inline]
+    pub(crate) fn handle_delete(&mut self, index: i32) -> Result<Entity> {
+        self.changes.clear();
+        let entity = self.entities.remove(&index).ok_or(Error::UnknownEntity)?;
+        self.remove_from_class_index(entity.serializer.serializer_name.hash, index);
         Ok(entity)
     }
 
+    // handle_update is a checked variant of [`Self::handle_update_unchecked`].
+    #[cfg(feature = "safe")]
+    #[inline]
+    pub(crate) fn handle_update(&mut self, index: i32, br: &mut BitReader) -> Result<&Entity> {
+        let entity = self.entities.get_mut(&index).ok_or(Error::UnknownEntity)?;
+        self.changes.clear();
+        entity.parse(br, &mut self.changes)?;
+        Ok(entity)
+    }
+
+    // changes returns list of fields that were written by the most recent
+    // create / update.
+    #[inline]
+    pub(crate) fn changes(&self) -> &[FieldChange] {
+        &self.changes
+    }
+
     // ----
 
     #[inline]
@@ -289,12 +600,45 @@ impl EntityContainer {
         self.entities.get(index)
     }
 
+    // resolve_handle returns entity that the handle points to; stale handles
+    // (those that point to a slot that was re-used by another entity) are
+    // rejected.
+    #[inline]
+    pub fn resolve_handle(&self, handle: u32) -> Option<&Entity> {
+        if handle == INVALID_NETWORKED_EHANDLE_VALUE {
+            return None;
+        }
+        self.entities
+            .get(&handle_to_index(handle))
+            .filter(|entity| {
+                entity.serial & NETWORKED_EHANDLE_SERIAL_MASK == handle_to_serial(handle)
+            })
+    }
+
+    // iter_by_class iterates over entities (in order of their indices) whose
+    // serializer name hash is equal to the given one.
+    #[inline]
+    pub fn iter_by_class(&self, serializer_name_hash: u64) -> impl Iterator<Item = &Entity> {
+        self.class_index
+            .get(&serializer_name_hash)
+            .into_iter()
+            .flatten()
+            .filter_map(|index| self.entities.get(index))
+    }
+
+    #[inline]
+    pub fn find_first_by_class(&self, serializer_name_hash: u64) -> Option<&Entity> {
+        self.iter_by_class(serializer_name_hash).next()
+    }
+
     // clear clears underlying storage, but this has no effect on the allocated
     // capacity.
     #[inline]
     pub fn clear(&mut self) {
         self.entities.clear();
         self.baseline_entities.clear();
+        self.changes.clear();
+        self.class_index.clear();
     }
 
     #[inline]
@@ -305,6 +649,17 @@ impl EntityContainer {
     // TODO: introduce something like get_entity method
 }
 
+// NOTE: only entities are serialized, baselines are internal state.
+#[cfg(feature = "serde")]
+impl serde::Serialize for EntityContainer {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_map(self.entities.iter())
+    }
+}
+
 // ----
 
 pub const fn make_field_key(path: &[&str]) -> u64 {
@@ -322,3 +677,510 @@ pub const fn make_field_key(path: &[&str]) -> u64 {
 
     hash
 }
+
+// make_item_key returns key of an item of a (fixed or dynamic) array; indices
+// are hashed as numbers, not as strings (see [`make_field_key`]).
+#[inline]
+pub const fn make_item_key(array_key: u64, index: usize) -> u64 {
+    fxhash::add_u64_to_hash(array_key, fxhash::add_u64_to_hash(0, index as u64))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        bitbuf::BitWriter,
+        fieldmetadata::FieldSpecialDescriptor,
+        flattenedserializers::make_symbol_map,
+        protos::{c_demo_class_info, CDemoClassInfo},
+    };
+
+    const CLASS_NAME: &str = "CTestEntity";
+    const OTHER_CLASS_NAME: &str = "COtherEntity";
+
+    // see fieldpath's exec_op for op codes; NOTE: all field paths are read
+    // before field values.
+    const PLUS_ONE: &[bool] = &[false];
+    const PLUS_TWO: &[bool] = &[true, true, true, false];
+    const FINISH: &[bool] = &[true, false];
+
+    fn write_op(bw: &mut BitWriter, op: &[bool]) {
+        op.iter().for_each(|bit| bw.write_bool(*bit));
+    }
+
+    fn make_serializer(class_name: &str) -> Shared<FlattenedSerializer> {
+        let make_field = |var_name: &str| {
+            let mut field = FlattenedSerializerField {
+                var_name: (&var_name.to_string()).into(),
+                ..Default::default()
+            };
+            field.metadata.decoder = Box::<fielddecoder::BoolDecoder>::default();
+            Shared::new(field)
+        };
+        Shared::new(FlattenedSerializer {
+            serializer_name: (&class_name.to_string()).into(),
+            fields: vec![make_field("m_bFirst"), make_field("m_bSecond")],
+            symbols: Shared::new(make_symbol_map(["m_bFirst", "m_bSecond"])),
+            ..Default::default()
+        })
+    }
+
+    fn make_container_with_baseline(first: bool, second: bool) -> Result<EntityContainer> {
+        let mut bw = BitWriter::default();
+        write_op(&mut bw, PLUS_ONE);
+        write_op(&mut bw, PLUS_ONE);
+        write_op(&mut bw, FINISH);
+        bw.write_bool(first);
+        bw.write_bool(second);
+        let data = bw.finish();
+
+        let mut entities = EntityContainer::new();
+        for (class_id, class_name) in [CLASS_NAME, OTHER_CLASS_NAME].into_iter().enumerate() {
+            let mut baseline = Entity {
+                index: -1,
+                serial: 0,
+                fields: HashMap::default(),
+                serializer: make_serializer(class_name),
+            };
+            baseline.parse(&mut BitReader::new(&data), &mut Vec::new())?;
+            entities.baseline_entities.insert(class_id as i32, baseline);
+        }
+        Ok(entities)
+    }
+
+    fn create(entities: &mut EntityContainer, index: i32, data: &[u8]) -> Result<()> {
+        let entity_classes = EntityClasses::parse(CDemoClassInfo {
+            classes: vec![
+                c_demo_class_info::ClassT {
+                    class_id: Some(0),
+                    network_name: Some(CLASS_NAME.to_string()),
+                    table_name: None,
+                },
+                c_demo_class_info::ClassT {
+                    class_id: Some(1),
+                    network_name: Some(OTHER_CLASS_NAME.to_string()),
+                    table_name: None,
+                },
+            ],
+        });
+        let serializers = FlattenedSerializerContainer::from_serializers([
+            make_serializer(CLASS_NAME),
+            make_serializer(OTHER_CLASS_NAME),
+        ]);
+        entities.handle_create(
+            index,
+            &mut BitReader::new(data),
+            &entity_classes,
+            &InstanceBaseline::default(),
+            &serializers,
+        )?;
+        Ok(())
+    }
+
+    // create_unchanged creates entity that only holds values from baseline.
+    fn create_unchanged(
+        entities: &mut EntityContainer,
+        index: i32,
+        class_id: i32,
+        serial: u32,
+    ) -> Result<()> {
+        // NOTE: there are 2 classes, class id takes 1 bit.
+        let mut bw = BitWriter::default();
+        bw.write_bool(class_id == 1);
+        bw.write_ubitlong(serial, NUM_SERIAL_NUM_BITS as usize);
+        bw.write_ubitlong(0, 8);
+        write_op(&mut bw, FINISH);
+        create(entities, index, &bw.finish())
+    }
+
+    fn delete(entities: &mut EntityContainer, index: i32) -> Result<()> {
+        #[cfg(feature = "safe")]
+        entities.handle_delete(index)?;
+        #[cfg(not(feature = "safe"))]
+        unsafe {
+            entities.handle_delete_unchecked(index);
+        }
+        Ok(())
+    }
+
+    fn update(entities: &mut EntityContainer, index: i32, data: &[u8]) -> Result<()> {
+        #[cfg(feature = "safe")]
+        entities.handle_update(index, &mut BitReader::new(data))?;
+        #[cfg(not(feature = "safe"))]
+        unsafe {
+            entities.handle_update_unchecked(index, &mut BitReader::new(data))?;
+        }
+        Ok(())
+    }
+
+    // NOTE: test entities consist only of bool fields.
+    fn changes(entities: &EntityContainer) -> Vec<(u64, Option<bool>)> {
+        entities
+            .changes()
+            .iter()
+            .map(|change| match change.prev_value {
+                Some(FieldValue::Bool(prev_value)) => (change.key, Some(prev_value)),
+                _ => (change.key, None),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_handle() {
+        let handle = make_handle(300, (1 << 12) | 5);
+        assert_eq!(handle_to_index(handle), 300);
+        assert_eq!(handle_to_serial(handle), 5);
+        assert_eq!(handle_to_index(INVALID_NETWORKED_EHANDLE_VALUE), 16383);
+        assert_eq!(INVALID_NETWORKED_EHANDLE_VALUE, 0xffffff);
+    }
+
+    #[test]
+    fn test_changes_on_create() -> Result<()> {
+        let mut entities = make_container_with_baseline(true, false)?;
+
+        // NOTE: there are 2 classes, class id takes 1 bit.
+        let mut bw = BitWriter::default();
+        bw.write_bool(false);
+        bw.write_ubitlong(7, NUM_SERIAL_NUM_BITS as usize);
+        bw.write_ubitlong(0, 8);
+        write_op(&mut bw, PLUS_TWO);
+        write_op(&mut bw, FINISH);
+        bw.write_bool(true);
+        create(&mut entities, 42, &bw.finish())?;
+
+        // NOTE: fields that were only decoded from baseline are not changes;
+        // previous values of changed fields are values from baseline.
+        assert_eq!(
+            changes(&entities),
+            [(make_field_key(&["m_bSecond"]), Some(false))]
+        );
+
+        let entity = entities.get(&42).ok_or(Error::UnknownEntity)?;
+        assert_eq!(entity.serial(), 7);
+        assert!(entity.get::<bool>(&make_field_key(&["m_bFirst"]))?);
+        assert!(entity.get::<bool>(&make_field_key(&["m_bSecond"]))?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_changes_on_update() -> Result<()> {
+        let mut entities = make_container_with_baseline(true, false)?;
+
+        let mut bw = BitWriter::default();
+        bw.write_bool(false);
+        bw.write_ubitlong(1, NUM_SERIAL_NUM_BITS as usize);
+        bw.write_ubitlong(0, 8);
+        write_op(&mut bw, FINISH);
+        create(&mut entities, 1, &bw.finish())?;
+        assert_eq!(changes(&entities), []);
+
+        let mut bw = BitWriter::default();
+        write_op(&mut bw, PLUS_ONE);
+        write_op(&mut bw, PLUS_ONE);
+        write_op(&mut bw, FINISH);
+        bw.write_bool(false);
+        bw.write_bool(true);
+        update(&mut entities, 1, &bw.finish())?;
+        assert_eq!(
+            changes(&entities),
+            [
+                (make_field_key(&["m_bFirst"]), Some(true)),
+                (make_field_key(&["m_bSecond"]), Some(false)),
+            ]
+        );
+
+        // NOTE: changes are not accumulated between updates.
+        let mut bw = BitWriter::default();
+        write_op(&mut bw, PLUS_TWO);
+        write_op(&mut bw, FINISH);
+        bw.write_bool(false);
+        update(&mut entities, 1, &bw.finish())?;
+        assert_eq!(
+            changes(&entities),
+            [(make_field_key(&["m_bSecond"]), Some(true))]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_resolve_handle() -> Result<()> {
+        let mut entities = make_container_with_baseline(false, false)?;
+        create_unchanged(&mut entities, 5, 0, 1)?;
+        let stale_handle = make_handle(5, 1);
+        let resolve_index =
+            |entities: &EntityContainer, handle| entities.resolve_handle(handle).map(Entity::index);
+        assert_eq!(resolve_index(&entities, stale_handle), Some(5));
+
+        // NOTE: the slot is re-used by another entity with a different serial.
+        delete(&mut entities, 5)?;
+        create_unchanged(&mut entities, 5, 0, 2)?;
+        assert_eq!(resolve_index(&entities, stale_handle), None);
+        assert_eq!(resolve_index(&entities, make_handle(5, 2)), Some(5));
+        assert_eq!(
+            resolve_index(&entities, INVALID_NETWORKED_EHANDLE_VALUE),
+            None
+        );
+
+        Ok(())
+    }
+
+    // NOTE: entities are cloned from baseline, index and serial must be the
+    // ones of the created entity, not the ones baseline was parsed with.
+    #[test]
+    fn test_create_from_baseline() -> Result<()> {
+        let mut entities = make_container_with_baseline(false, false)?;
+        create_unchanged(&mut entities, 3, 0, 1)?;
+        create_unchanged(&mut entities, 7, 0, 2)?;
+
+        for (index, serial) in [(3, 1), (7, 2)] {
+            let entity = entities.get(&index).ok_or(Error::UnknownEntity)?;
+            assert_eq!(entity.index(), index);
+            assert_eq!(entity.serial(), serial);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_class_index() -> Result<()> {
+        const CLASS_HASH: u64 = fxhash::hash_bytes(CLASS_NAME.as_bytes());
+        const OTHER_CLASS_HASH: u64 = fxhash::hash_bytes(OTHER_CLASS_NAME.as_bytes());
+
+        let indices = |entities: &EntityContainer, serializer_name_hash: u64| -> Vec<i32> {
+            entities
+                .iter_by_class(serializer_name_hash)
+                .map(|entity| entity.index())
+                .collect()
+        };
+        let first_index = |entities: &EntityContainer, serializer_name_hash: u64| {
+            entities
+                .find_first_by_class(serializer_name_hash)
+                .map(|entity| entity.index())
+        };
+
+        let mut entities = make_container_with_baseline(false, false)?;
+        create_unchanged(&mut entities, 3, 0, 1)?;
+        create_unchanged(&mut entities, 1, 0, 1)?;
+        create_unchanged(&mut entities, 2, 1, 1)?;
+        assert_eq!(indices(&entities, CLASS_HASH), [1, 3]);
+        assert_eq!(indices(&entities, OTHER_CLASS_HASH), [2]);
+        assert_eq!(first_index(&entities, CLASS_HASH), Some(1));
+        assert_eq!(first_index(&entities, OTHER_CLASS_HASH), Some(2));
+
+        delete(&mut entities, 1)?;
+        assert_eq!(indices(&entities, CLASS_HASH), [3]);
+        assert_eq!(first_index(&entities, CLASS_HASH), Some(3));
+
+        // NOTE: index can be re-used by entity of a different class without
+        // the previous one being deleted.
+        create_unchanged(&mut entities, 3, 1, 2)?;
+        assert!(indices(&entities, CLASS_HASH).is_empty());
+        assert_eq!(indices(&entities, OTHER_CLASS_HASH), [2, 3]);
+        assert_eq!(first_index(&entities, CLASS_HASH), None);
+
+        entities.clear();
+        assert!(indices(&entities, OTHER_CLASS_HASH).is_empty());
+        assert_eq!(first_index(&entities, OTHER_CLASS_HASH), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_name() -> Result<()> {
+        let make_field = |var_name: &str| FlattenedSerializerField {
+            var_name: (&var_name.to_string()).into(),
+            ..Default::default()
+        };
+        let make_serializer = |fields: Vec<FlattenedSerializerField>| {
+            Shared::new(FlattenedSerializer {
+                fields: fields.into_iter().map(Shared::new).collect(),
+                ..Default::default()
+            })
+        };
+        let inner = make_serializer(vec![make_field("m_flValue")]);
+
+        let mut struct_field = make_field("m_pEntity");
+        struct_field.field_serializer = Some(inner.clone());
+
+        let mut fixed_array_field = make_field("m_iValues");
+        fixed_array_field.metadata.special_descriptor =
+            Some(FieldSpecialDescriptor::FixedArray { length: 2 });
+        let mut item = fixed_array_field.clone();
+        item.metadata.special_descriptor = None;
+        item.field_serializer = Some(inner.clone());
+        fixed_array_field.field_serializer = Some(make_serializer(vec![item.clone(), item]));
+
+        let mut dynamic_array_field = make_field("m_vecValues");
+        dynamic_array_field.metadata.special_descriptor =
+            Some(FieldSpecialDescriptor::DynamicSerializerArray);
+        dynamic_array_field.field_serializer =
+            Some(make_serializer(vec![FlattenedSerializerField {
+                field_serializer: Some(inner),
+                ..Default::default()
+            }]));
+
+        let serializer = FlattenedSerializer {
+            fields: vec![
+                Shared::new(struct_field),
+                Shared::new(fixed_array_field),
+                Shared::new(dynamic_array_field),
+            ],
+            symbols: Shared::new(make_symbol_map([
+                "m_pEntity",
+                "m_iValues",
+                "m_vecValues",
+                "m_flValue",
+            ])),
+            ..Default::default()
+        };
+
+        let mut entity = Entity {
+            index: 0,
+            serial: 0,
+            fields: HashMap::default(),
+            serializer: Shared::new(serializer),
+        };
+        let mut insert = |path: &str, value: FieldValue| -> Result<u64> {
+            let key = entity
+                .serializer
+                .resolve_field_key(path)
+                .ok_or(Error::InvalidFieldPath)?;
+            entity.fields.insert(
+                key,
+                EntityField {
+                    #[cfg(feature = "preserve-metadata")]
+                    path: FieldPath::default(),
+                    value,
+                },
+            );
+            Ok(key)
+        };
+        let paths = [
+            "m_pEntity.m_flValue",
+            "m_iValues.0.m_flValue",
+            "m_iValues.1.m_flValue",
+            "m_vecValues",
+            "m_vecValues.0.m_flValue",
+            "m_vecValues.1.m_flValue",
+        ];
+        let mut keys = Vec::with_capacity(paths.len());
+        for path in paths {
+            // NOTE: value of dynamic array's own field is its length.
+            let value = if path == "m_vecValues" {
+                FieldValue::U32(2)
+            } else {
+                FieldValue::F32(1.0)
+            };
+            keys.push(insert(path, value)?);
+        }
+
+        for (path, key) in paths.iter().zip(keys.iter()) {
+            assert_eq!(entity.get_name(key).as_deref(), Some(*path));
+        }
+        // NOTE: items beyond length of dynamic array can't be named.
+        let key = entity
+            .serializer
+            .resolve_field_key("m_vecValues.2.m_flValue")
+            .ok_or(Error::InvalidFieldPath)?;
+        assert_eq!(entity.get_name(&key), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_array_and_vec() -> Result<()> {
+        let mut entity = Entity {
+            index: 0,
+            serial: 0,
+            fields: HashMap::default(),
+            serializer: Shared::new(FlattenedSerializer::default()),
+        };
+        let mut insert = |key: u64, value: FieldValue| {
+            entity.fields.insert(
+                key,
+                EntityField {
+                    #[cfg(feature = "preserve-metadata")]
+                    path: FieldPath::default(),
+                    value,
+                },
+            );
+        };
+        const FIXED_KEY: u64 = make_field_key(&["m_iValues"]);
+        const DYNAMIC_KEY: u64 = make_field_key(&["m_vecValues"]);
+        for index in 0..2 {
+            insert(
+                make_item_key(FIXED_KEY, index),
+                FieldValue::I32(index as i32),
+            );
+        }
+        insert(DYNAMIC_KEY, FieldValue::U32(3));
+        for index in 0..3 {
+            insert(
+                make_item_key(DYNAMIC_KEY, index),
+                FieldValue::I32(index as i32),
+            );
+        }
+
+        assert_eq!(entity.get_array::<i32, 2>(&FIXED_KEY)?, [0, 1]);
+        assert!(matches!(
+            entity.get_array::<i32, 3>(&FIXED_KEY),
+            Err(Error::MissingField(_))
+        ));
+        assert_eq!(entity.get_vec::<i32>(&DYNAMIC_KEY)?, vec![0, 1, 2]);
+        assert!(matches!(
+            entity.get_vec::<i32>(&FIXED_KEY),
+            Err(Error::MissingField(_))
+        ));
+
+        Ok(())
+    }
+
+    // NOTE: entities can't be deserialized back (they can't exist without
+    // serializers), but exported fields must deserialize into values that
+    // entity holds.
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_export() -> std::result::Result<(), Box<dyn std::error::Error>> {
+        #[derive(serde::Deserialize)]
+        struct ExportedEntity {
+            index: i32,
+            #[cfg(feature = "preserve-metadata")]
+            serializer: String,
+            #[cfg(not(feature = "preserve-metadata"))]
+            serializer: u64,
+            fields: std::collections::HashMap<String, FieldValue>,
+        }
+
+        let mut entities = make_container_with_baseline(true, false)?;
+        let mut bw = BitWriter::default();
+        bw.write_bool(false);
+        bw.write_ubitlong(1, NUM_SERIAL_NUM_BITS as usize);
+        bw.write_ubitlong(0, 8);
+        write_op(&mut bw, FINISH);
+        create(&mut entities, 3, &bw.finish())?;
+
+        let json = serde_json::to_string(&entities)?;
+        let exported: std::collections::HashMap<i32, ExportedEntity> = serde_json::from_str(&json)?;
+        let exported = exported.get(&3).ok_or(Error::UnknownEntity)?;
+        assert_eq!(exported.index, 3);
+
+        let entity = entities.get(&3).ok_or(Error::UnknownEntity)?;
+        let serializer_name = &entity.get_serializer().serializer_name;
+        #[cfg(feature = "preserve-metadata")]
+        assert_eq!(exported.serializer, serializer_name.str.as_ref());
+        #[cfg(not(feature = "preserve-metadata"))]
+        assert_eq!(exported.serializer, serializer_name.hash);
+        assert_eq!(exported.fields.len(), 2);
+        for (key, value) in entity.iter() {
+            #[cfg(feature = "preserve-metadata")]
+            let name = entity.get_name(key).ok_or(Error::MissingField(*key))?;
+            #[cfg(not(feature = "preserve-metadata"))]
+            let name = key.to_string();
+            assert_eq!(exported.fields.get(&name), Some(value));
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,158 @@
+#[cfg(feature = "broadcast-client")]
+use crate::broadcastclient;
+use crate::{
+    bitbuf, broadcast, demofile, demoindex, demosource, entities, fieldvalue, flattenedserializers,
+    gameevents, instancebaseline, protos::EDemoCommands, stringtables,
+};
+use std::fmt;
+
+// Error is a crate-wide error. errors that occur while handling commands are
+// wrapped into [`Error::Context`] which points at the place in the replay where
+// things went wrong; use [`Error::inner`] to get to the actual error.
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    // std
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    // external
+    #[error(transparent)]
+    Prost(#[from] prost::DecodeError),
+    #[error(transparent)]
+    Snap(#[from] snap::Error),
+    // crate
+    #[error(transparent)]
+    BitBuf(#[from] bitbuf::Error),
+    #[error(transparent)]
+    Broadcast(#[from] broadcast::Error),
+    #[cfg(feature = "broadcast-client")]
+    #[error(transparent)]
+    BroadcastClient(#[from] broadcastclient::Error),
+    #[error(transparent)]
+    DemoFile(#[from] demofile::Error),
+    #[error(transparent)]
+    DemoIndex(#[from] demoindex::Error),
+    #[error(transparent)]
+    DemoSource(#[from] demosource::Error),
+    #[error(transparent)]
+    Entities(#[from] entities::Error),
+    #[error(transparent)]
+    FieldValue(#[from] fieldvalue::Error),
+    #[error(transparent)]
+    FlattenedSerializers(#[from] flattenedserializers::Error),
+    #[error(transparent)]
+    GameEvents(#[from] gameevents::Error),
+    #[error(transparent)]
+    InstanceBaseline(#[from] instancebaseline::Error),
+    #[error(transparent)]
+    StringTables(#[from] stringtables::Error),
+    // mod
+    #[error("packet entities arrived before entity classes or flattened serializers")]
+    MissingSignon,
+    #[error("packet message size {0} exceeds buffer size")]
+    OversizedPacketMessage(usize),
+    // NOTE: Display of wrapping errors does not include source errors; walk
+    // the chain with std::error::Error::source to get to them.
+    #[error("could not handle entity {index}")]
+    Entity {
+        index: i32,
+        #[source]
+        source: entities::Error,
+    },
+    #[error("at {context}")]
+    Context {
+        context: Box<ErrorContext>,
+        #[source]
+        source: Box<Error>,
+    },
+    // NOTE: Other exists mostly for visitors; they are free to return errors
+    // of their own.
+    #[error("{0}")]
+    Other(#[from] Box<dyn std::error::Error + Send + Sync>),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    // inner strips location context.
+    pub fn inner(&self) -> &Error {
+        match self {
+            Self::Context { source, .. } => source.inner(),
+            _ => self,
+        }
+    }
+
+    pub fn context(&self) -> Option<&ErrorContext> {
+        match self {
+            Self::Context { context, .. } => Some(context),
+            _ => None,
+        }
+    }
+}
+
+// ErrorContext describes where in the replay an error occurred.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ErrorContext {
+    // offset of the cmd header within the demo file; is none if offset could
+    // not be determined (e.g. if cmd could not be read).
+    pub offset: Option<u64>,
+    pub tick: i32,
+    pub cmd: EDemoCommands,
+    // packet_type is set if the error occurred while handling a message within
+    // a packet (see SvcMessages, EBaseGameEvents, etc.).
+    pub packet_type: Option<u32>,
+}
+
+impl fmt::Display for ErrorContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(offset) = self.offset {
+            write!(f, "offset {offset}, ")?;
+        }
+        write!(f, "tick {}, cmd {:?}", self.tick, self.cmd)?;
+        if let Some(packet_type) = self.packet_type {
+            write!(f, ", packet type {packet_type}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::error::Error as _;
+
+    #[test]
+    fn test_display() {
+        let err = Error::Context {
+            context: Box::new(ErrorContext {
+                offset: Some(16),
+                tick: 42,
+                cmd: EDemoCommands::DemPacket,
+                packet_type: Some(55),
+            }),
+            source: Box::new(Error::Entity {
+                index: 7,
+                source: entities::Error::UnknownEntity,
+            }),
+        };
+        assert_eq!(
+            err.to_string(),
+            "at offset 16, tick 42, cmd DemPacket, packet type 55"
+        );
+
+        let source = err.source().map(ToString::to_string);
+        assert_eq!(source.as_deref(), Some("could not handle entity 7"));
+
+        let source = err
+            .source()
+            .and_then(|err| err.source())
+            .map(ToString::to_string);
+        assert_eq!(source, Some(entities::Error::UnknownEntity.to_string()));
+        assert!(matches!(
+            err.inner(),
+            Error::Entity {
+                index: 7,
+                source: entities::Error::UnknownEntity
+            }
+        ));
+    }
+}
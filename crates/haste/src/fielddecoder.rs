@@ -26,8 +26,26 @@ pub type Result<T> = std::result::Result<T, Error>;
 // NOTE: PropTypeFns is what you are looking for, it has all the encoders,
 // decoders, proxies and all of the stuff.
 
-pub trait FieldDecode: DynClone + Debug {
+// MaybeSendSync is Send + Sync with send feature and nothing without it;
+// decoders are shared between parsers (and threads) only with send feature.
+#[cfg(feature = "send")]
+pub trait MaybeSendSync: Send + Sync {}
+#[cfg(feature = "send")]
+impl<T: Send + Sync> MaybeSendSync for T {}
+
+#[cfg(not(feature = "send"))]
+pub trait MaybeSendSync {}
+#[cfg(not(feature = "send"))]
+impl<T> MaybeSendSync for T {}
+
+pub trait FieldDecode: DynClone + Debug + MaybeSendSync {
     fn decode(&self, br: &mut BitReader) -> Result<FieldValue>;
+
+    // value_type_name returns [`FieldValue::type_name`] of values that decode
+    // produces; it is meant to be used by code generators.
+    fn value_type_name(&self) -> Option<&'static str> {
+        None
+    }
 }
 
 dyn_clone::clone_trait_object!(FieldDecode);
@@ -54,6 +72,11 @@ impl FieldDecode for I8Decoder {
             .map(|v| FieldValue::I8(v as i8))
             .map_err(Error::from)
     }
+
+    #[inline]
+    fn value_type_name(&self) -> Option<&'static str> {
+        Some("I8")
+    }
 }
 
 // ----
@@ -68,6 +91,11 @@ impl FieldDecode for I16Decoder {
             .map(|v| FieldValue::I16(v as i16))
             .map_err(Error::from)
     }
+
+    #[inline]
+    fn value_type_name(&self) -> Option<&'static str> {
+        Some("I16")
+    }
 }
 
 // ----
@@ -80,6 +108,11 @@ impl FieldDecode for I32Decoder {
     fn decode(&self, br: &mut BitReader) -> Result<FieldValue> {
         br.read_varint32().map(FieldValue::I32).map_err(Error::from)
     }
+
+    #[inline]
+    fn value_type_name(&self) -> Option<&'static str> {
+        Some("I32")
+    }
 }
 
 // ----
@@ -92,6 +125,11 @@ impl FieldDecode for I64Decoder {
     fn decode(&self, br: &mut BitReader) -> Result<FieldValue> {
         br.read_varint64().map(FieldValue::I64).map_err(Error::from)
     }
+
+    #[inline]
+    fn value_type_name(&self) -> Option<&'static str> {
+        Some("I64")
+    }
 }
 
 // ----
@@ -106,6 +144,11 @@ impl FieldDecode for U8Decoder {
             .map(|v| FieldValue::U8(v as u8))
             .map_err(Error::from)
     }
+
+    #[inline]
+    fn value_type_name(&self) -> Option<&'static str> {
+        Some("U8")
+    }
 }
 // ----
 
@@ -119,6 +162,11 @@ impl FieldDecode for U16Decoder {
             .map(|v| FieldValue::U16(v as u16))
             .map_err(Error::from)
     }
+
+    #[inline]
+    fn value_type_name(&self) -> Option<&'static str> {
+        Some("U16")
+    }
 }
 // ----
 
@@ -132,6 +180,11 @@ impl FieldDecode for U32Decoder {
             .map(FieldValue::U32)
             .map_err(Error::from)
     }
+
+    #[inline]
+    fn value_type_name(&self) -> Option<&'static str> {
+        Some("U32")
+    }
 }
 
 // ----
@@ -184,6 +237,11 @@ impl FieldDecode for U64Decoder {
     fn decode(&self, br: &mut BitReader) -> Result<FieldValue> {
         self.decoder.decode(br)
     }
+
+    #[inline]
+    fn value_type_name(&self) -> Option<&'static str> {
+        Some("U64")
+    }
 }
 
 // ----
@@ -196,11 +254,16 @@ impl FieldDecode for BoolDecoder {
     fn decode(&self, br: &mut BitReader) -> Result<FieldValue> {
         br.read_bool().map(FieldValue::Bool).map_err(Error::from)
     }
+
+    #[inline]
+    fn value_type_name(&self) -> Option<&'static str> {
+        Some("Bool")
+    }
 }
 
 // ----
 
-trait InternalF32Decode: DynClone + Debug {
+trait InternalF32Decode: DynClone + Debug + MaybeSendSync {
     fn decode(&self, br: &mut BitReader) -> Result<f32>;
 }
 
@@ -255,6 +318,11 @@ impl FieldDecode for QuantizedFloatDecoder {
             .map(FieldValue::F32)
             .map_err(Error::from)
     }
+
+    #[inline]
+    fn value_type_name(&self) -> Option<&'static str> {
+        Some("F32")
+    }
 }
 
 // ----
@@ -388,6 +456,11 @@ impl FieldDecode for F32Decoder {
             .map(FieldValue::F32)
             .map_err(Error::from)
     }
+
+    #[inline]
+    fn value_type_name(&self) -> Option<&'static str> {
+        Some("F32")
+    }
 }
 
 // ----
@@ -447,6 +520,11 @@ impl FieldDecode for VectorDecoder {
     fn decode(&self, br: &mut BitReader) -> Result<FieldValue> {
         self.decoder.decode(br)
     }
+
+    #[inline]
+    fn value_type_name(&self) -> Option<&'static str> {
+        Some("Vector")
+    }
 }
 
 // ----
@@ -474,6 +552,11 @@ impl FieldDecode for Vector2DDecoder {
         ];
         Ok(FieldValue::Vector2D(vec2))
     }
+
+    #[inline]
+    fn value_type_name(&self) -> Option<&'static str> {
+        Some("Vector2D")
+    }
 }
 
 // ----
@@ -502,6 +585,11 @@ impl FieldDecode for Vector4DDecoder {
         ];
         Ok(FieldValue::Vector4D(vec4))
     }
+
+    #[inline]
+    fn value_type_name(&self) -> Option<&'static str> {
+        Some("Vector4D")
+    }
 }
 
 // ----
@@ -626,6 +714,11 @@ impl FieldDecode for QAngleDecoder {
     fn decode(&self, br: &mut BitReader) -> Result<FieldValue> {
         self.decoder.decode(br)
     }
+
+    #[inline]
+    fn value_type_name(&self) -> Option<&'static str> {
+        Some("QAngle")
+    }
 }
 
 // ----
@@ -644,4 +737,9 @@ impl FieldDecode for StringDecoder {
             std::str::from_utf8_unchecked(&buf[..n])
         })))
     }
+
+    #[inline]
+    fn value_type_name(&self) -> Option<&'static str> {
+        Some("String")
+    }
 }
@@ -71,6 +71,11 @@ pub enum FieldSpecialDescriptor {
 }
 
 impl FieldSpecialDescriptor {
+    #[inline(always)]
+    pub(crate) fn is_fixed_array(&self) -> bool {
+        matches!(self, Self::FixedArray { .. })
+    }
+
     #[inline(always)]
     pub(crate) fn is_dynamic_array(&self) -> bool {
         matches!(
@@ -167,6 +172,8 @@ fn visit_ident(
         // other custom types
         "CUtlSymbolLarge" => non_special!(StringDecoder),
         "CUtlString" => non_special!(StringDecoder),
+        // NOTE: this was discovered in cs2, but is not specific to it.
+        "CGlobalSymbol" => non_special!(StringDecoder),
         // public/mathlib/vector.h
         "QAngle" => non_special!(QAngleDecoder::new(field)),
         "CNetworkedQuantizedFloat" => {
@@ -342,3 +349,51 @@ pub fn get_field_metadata(
 // item_definition_index_t // game/shared/econ/econ_item_constants.h
 // itemid_t // game/shared/econ/econ_item_constants.h
 // style_index_t // game/shared/econ/econ_item_constants.h
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        bitbuf::{BitReader, BitWriter},
+        fieldvalue::FieldValue,
+        vartype,
+    };
+
+    fn decode(
+        var_type: &str,
+        data: &[u8],
+    ) -> std::result::Result<FieldValue, Box<dyn std::error::Error>> {
+        let field = FlattenedSerializerField::default();
+        let ctx = FlattenedSerializerContext {
+            tick_interval: 1.0 / 64.0,
+        };
+        let field_metadata = get_field_metadata(vartype::parse(var_type)?, &field, &ctx)?;
+        assert!(field_metadata.special_descriptor.is_none());
+        let mut br = BitReader::new(data);
+        Ok(field_metadata.decoder.decode(&mut br)?)
+    }
+
+    #[test]
+    fn test_cs2_var_types() -> std::result::Result<(), Box<dyn std::error::Error>> {
+        let mut bw = BitWriter::default();
+        bw.write_bytes(b"weapon_ak47\0");
+        assert_eq!(
+            decode("CGlobalSymbol", &bw.finish())?,
+            FieldValue::String("weapon_ak47".into())
+        );
+
+        // NOTE: CUtlStringToken and GameTick_t are covered by the default arm.
+        let mut bw = BitWriter::default();
+        bw.write_uvarint32(1058891786);
+        assert_eq!(
+            decode("CUtlStringToken", &bw.finish())?,
+            FieldValue::U32(1058891786)
+        );
+
+        let mut bw = BitWriter::default();
+        bw.write_uvarint32(128);
+        assert_eq!(decode("GameTick_t", &bw.finish())?, FieldValue::U32(128));
+
+        Ok(())
+    }
+}
@@ -64,6 +64,8 @@ impl FieldPath {
 
     // ----
 
+    // NOTE: with safe feature entities use checked variants.
+    #[cfg_attr(feature = "safe", allow(dead_code))]
     #[inline(always)]
     pub(crate) unsafe fn get_unchecked(&self, index: usize) -> usize {
         *self.data.get_unchecked(index) as usize
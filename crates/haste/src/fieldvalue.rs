@@ -1,3 +1,15 @@
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    // mod
+    #[error("incompatible types: {got} cannot be converted into {want}")]
+    IncompatibleTypes {
+        want: &'static str,
+        got: &'static str,
+    },
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
 // from public/dt_common.h
 //
 // typedef enum
@@ -18,7 +30,8 @@
 
 // NOTE: Clone derive is needed here because Entity in entities.rs needs to be
 // clonable which means that all members of it also should be clonable.
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum FieldValue {
     I8(i8),
     I16(i16),
@@ -98,3 +111,135 @@ impl std::fmt::Debug for FieldValue {
         }
     }
 }
+
+// ----
+
+impl FieldValue {
+    #[inline]
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Self::I8(_) => "I8",
+            Self::I16(_) => "I16",
+            Self::I32(_) => "I32",
+            Self::I64(_) => "I64",
+
+            Self::U8(_) => "U8",
+            Self::U16(_) => "U16",
+            Self::U32(_) => "U32",
+            Self::U64(_) => "U64",
+
+            Self::Bool(_) => "Bool",
+            Self::F32(_) => "F32",
+
+            Self::Vector(_) => "Vector",
+            Self::Vector2D(_) => "Vector2D",
+            Self::Vector4D(_) => "Vector4D",
+            Self::QAngle(_) => "QAngle",
+
+            Self::String(_) => "String",
+        }
+    }
+}
+
+// NOTE: conversions only allow lossless widening (e.g. U8 can be converted
+// into u32 or i16, but I32 can't be converted into u32); this is enforced by
+// From impls of std.
+macro_rules! impl_try_from_field_value {
+    ($ty:ty => $($variant:ident),+) => {
+        impl TryFrom<&FieldValue> for $ty {
+            type Error = Error;
+
+            #[inline]
+            fn try_from(value: &FieldValue) -> Result<Self> {
+                match value {
+                    $(FieldValue::$variant(value) => Ok(Self::from(*value)),)+
+                    _ => Err(Error::IncompatibleTypes {
+                        want: stringify!($ty),
+                        got: value.type_name(),
+                    }),
+                }
+            }
+        }
+    };
+}
+
+impl_try_from_field_value!(i8 => I8);
+impl_try_from_field_value!(i16 => I8, I16, U8);
+impl_try_from_field_value!(i32 => I8, I16, I32, U8, U16);
+impl_try_from_field_value!(i64 => I8, I16, I32, I64, U8, U16, U32);
+
+impl_try_from_field_value!(u8 => U8);
+impl_try_from_field_value!(u16 => U8, U16);
+impl_try_from_field_value!(u32 => U8, U16, U32);
+impl_try_from_field_value!(u64 => U8, U16, U32, U64);
+
+impl_try_from_field_value!(bool => Bool);
+impl_try_from_field_value!(f32 => F32);
+
+impl_try_from_field_value!([f32; 3] => Vector, QAngle);
+impl_try_from_field_value!([f32; 2] => Vector2D);
+impl_try_from_field_value!([f32; 4] => Vector4D);
+
+impl<'a> TryFrom<&'a FieldValue> for &'a str {
+    type Error = Error;
+
+    #[inline]
+    fn try_from(value: &'a FieldValue) -> Result<Self> {
+        match value {
+            FieldValue::String(value) => Ok(value.as_ref()),
+            _ => Err(Error::IncompatibleTypes {
+                want: "&str",
+                got: value.type_name(),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_try_from() -> Result<()> {
+        assert_eq!(u32::try_from(&FieldValue::U8(42))?, 42);
+        assert_eq!(i64::try_from(&FieldValue::U32(u32::MAX))?, u32::MAX as i64);
+        assert_eq!(
+            <[f32; 3]>::try_from(&FieldValue::QAngle([1.0, 2.0, 3.0]))?,
+            [1.0, 2.0, 3.0]
+        );
+        assert_eq!(
+            <&str>::try_from(&FieldValue::String("haste".into()))?,
+            "haste"
+        );
+
+        assert!(matches!(
+            u32::try_from(&FieldValue::I32(-1)),
+            Err(Error::IncompatibleTypes {
+                want: "u32",
+                got: "I32"
+            })
+        ));
+        assert!(u8::try_from(&FieldValue::U16(1)).is_err());
+        Ok(())
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_roundtrip() -> std::result::Result<(), serde_json::Error> {
+        let values = vec![
+            FieldValue::I8(-8),
+            FieldValue::I64(i64::MIN),
+            FieldValue::U64(u64::MAX),
+            FieldValue::Bool(true),
+            FieldValue::F32(0.25),
+            FieldValue::Vector([1.0, 2.0, 3.0]),
+            FieldValue::Vector2D([1.0, 2.0]),
+            FieldValue::Vector4D([1.0, 2.0, 3.0, 4.0]),
+            FieldValue::QAngle([0.0, 90.0, 180.0]),
+            FieldValue::String("haste".into()),
+        ];
+        let json = serde_json::to_string(&values)?;
+        assert_eq!(serde_json::from_str::<Vec<FieldValue>>(&json)?, values);
+        Ok(())
+    }
+}
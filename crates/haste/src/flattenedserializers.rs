@@ -6,12 +6,12 @@ use crate::{
         CDemoSendTables, CsvcMsgFlattenedSerializer, ProtoFlattenedSerializerFieldT,
         ProtoFlattenedSerializerT,
     },
-    vartype,
+    vartype, Shared,
 };
 use dungers::varint;
 use hashbrown::{hash_map::Values, HashMap};
 use nohash::NoHashHasher;
-use std::{hash::BuildHasherDefault, rc::Rc};
+use std::hash::BuildHasherDefault;
 
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
@@ -76,8 +76,9 @@ pub struct FlattenedSerializerContext {
 /// "highest" version of serializer.
 ///
 /// it might be reasonable to actually incorporate it if there will be a need to run this parser in
-/// an environment that processes high volumes of replays. theoretically flattened serializers can
-/// be parsed once and then reused for future parse passes.
+/// an environment that processes high volumes of replays. flattened serializers can be parsed once
+/// and then reused for future parse passes (see [SerializerCache]); the cache is keyed by send
+/// tables blob thus versions are covered.
 #[derive(Debug, Clone, Default)]
 pub struct FlattenedSerializerField {
     pub var_type: Symbol,
@@ -89,7 +90,7 @@ pub struct FlattenedSerializerField {
     pub field_serializer_name: Option<Symbol>,
     pub var_encoder: Option<Symbol>,
 
-    pub field_serializer: Option<Rc<FlattenedSerializer>>,
+    pub field_serializer: Option<Shared<FlattenedSerializer>>,
     pub metadata: FieldMetadata,
 }
 
@@ -139,6 +140,8 @@ impl FlattenedSerializerField {
         Ok(ret)
     }
 
+    // NOTE: with safe feature entities use checked variants.
+    #[cfg_attr(feature = "safe", allow(dead_code))]
     #[inline(always)]
     pub(crate) unsafe fn get_child_unchecked(&self, index: usize) -> &Self {
         let fs = self.field_serializer.as_ref();
@@ -161,6 +164,14 @@ impl FlattenedSerializerField {
         self.var_encoder.as_ref().is_some_and(|lhs| lhs.hash == rhs)
     }
 
+    #[inline(always)]
+    pub fn is_fixed_array(&self) -> bool {
+        self.metadata
+            .special_descriptor
+            .as_ref()
+            .is_some_and(|sd| sd.is_fixed_array())
+    }
+
     #[inline(always)]
     pub fn is_dynamic_array(&self) -> bool {
         self.metadata
@@ -183,11 +194,19 @@ impl FlattenedSerializerField {
 #[derive(Debug, Clone, Default)]
 pub struct FlattenedSerializer {
     pub serializer_name: Symbol,
-    pub fields: Vec<Rc<FlattenedSerializerField>>,
+    pub fields: Vec<Shared<FlattenedSerializerField>>,
+    // NOTE: symbols are shared by all serializers of a container; field names
+    // are built from them on first use (see [`Self::field_names`]).
+    pub(crate) symbols: Shared<SymbolMap>,
+    pub(crate) field_names: FieldNamesCell,
 }
 
 impl FlattenedSerializer {
-    fn new(msg: &CsvcMsgFlattenedSerializer, fs: &ProtoFlattenedSerializerT) -> Result<Self> {
+    fn new(
+        msg: &CsvcMsgFlattenedSerializer,
+        fs: &ProtoFlattenedSerializerT,
+        symbols: Shared<SymbolMap>,
+    ) -> Result<Self> {
         // SAFETY: some symbols are cricual, if they don't exist - fail early
         // and loudly.
         let resolve_sym_unchecked = |i: i32| unsafe { msg.symbols.get_unchecked(i as usize) };
@@ -201,9 +220,19 @@ impl FlattenedSerializer {
         Ok(Self {
             serializer_name: Symbol::from(serializer_name),
             fields: Vec::with_capacity(fs.fields_index.len()),
+            symbols,
+            field_names: FieldNamesCell::default(),
         })
     }
 
+    #[inline]
+    pub(crate) fn field_names(&self) -> &FieldNames {
+        self.field_names
+            .get_or_init(|| FieldNames::new(self, &self.symbols))
+    }
+
+    // NOTE: with safe feature entities use checked variants.
+    #[cfg_attr(feature = "safe", allow(dead_code))]
     #[inline(always)]
     pub(crate) unsafe fn get_child_unchecked(&self, index: usize) -> &FlattenedSerializerField {
         debug_assert!(
@@ -220,11 +249,249 @@ impl FlattenedSerializer {
     pub fn get_child(&self, index: usize) -> Option<&FlattenedSerializerField> {
         self.fields.get(index).map(|field| field.as_ref())
     }
+
+    // resolve_field_key computes a field key from a dotted path (e.g.
+    // "m_vecStatViewerModifierValues.0.m_flValue") by walking the serializer
+    // the same way entities do when they compute keys of decoded fields.
+    //
+    // NOTE: unlike [`crate::entities::make_field_key`] this can address items of
+    // dynamic arrays (their indices are hashed as numbers, not as strings). but
+    // this is a lot more expensive, consider caching the result.
+    pub fn resolve_field_key(&self, path: &str) -> Option<u64> {
+        let mut parts = path.split('.');
+
+        let var_name_hash = fxhash::hash_bytes(parts.next()?.as_bytes());
+        let mut field = self
+            .fields
+            .iter()
+            .find(|field| field.var_name.hash == var_name_hash)?
+            .as_ref();
+        let mut field_key = field.var_name.hash;
+
+        for part in parts {
+            if field.is_dynamic_array() {
+                let index: u64 = part.parse().ok()?;
+                field = field.get_child(0)?;
+                field_key = fxhash::add_u64_to_hash(field_key, fxhash::add_u64_to_hash(0, index));
+            } else if field.is_fixed_array() {
+                // NOTE: items of fixed arrays are addressed by index, same as
+                // items of dynamic arrays.
+                let index: usize = part.parse().ok()?;
+                field = field.get_child(index)?;
+                field_key =
+                    fxhash::add_u64_to_hash(field_key, fxhash::add_u64_to_hash(0, index as u64));
+            } else {
+                let var_name_hash = fxhash::hash_bytes(part.as_bytes());
+                field = field
+                    .field_serializer
+                    .as_ref()?
+                    .fields
+                    .iter()
+                    .find(|field| field.var_name.hash == var_name_hash)?;
+                field_key = fxhash::add_u64_to_hash(field_key, field.var_name.hash);
+            }
+        }
+
+        Some(field_key)
+    }
+
+    // field_name is the reverse of [`Self::resolve_field_key`]; it builds a
+    // dotted path from field path (indices of fields within serializers).
+    #[cfg(feature = "preserve-metadata")]
+    pub fn field_name(&self, fp: &crate::fieldpath::FieldPath) -> Option<String> {
+        let mut field = self.get_child(fp.get(0)?)?;
+        let mut name = String::from(field.var_name.str.as_ref());
+        for i in 1..=fp.last() {
+            let index = fp.get(i)?;
+            name.push('.');
+            if field.is_dynamic_array() {
+                field = field.get_child(0)?;
+                name.push_str(&index.to_string());
+            } else if field.is_fixed_array() {
+                // NOTE: items of fixed arrays have the same name as the array
+                // itself, they are named by index instead.
+                field = field.get_child(index)?;
+                name.push_str(&index.to_string());
+            } else {
+                field = field.get_child(index)?;
+                name.push_str(&field.var_name.str);
+            }
+        }
+        Some(name)
+    }
 }
 
-type FieldMap = HashMap<i32, Rc<FlattenedSerializerField>, BuildHasherDefault<NoHashHasher<i32>>>;
-type SerializerMap = HashMap<u64, Rc<FlattenedSerializer>, BuildHasherDefault<NoHashHasher<u64>>>;
+pub(crate) type SymbolMap = HashMap<u64, Box<str>, BuildHasherDefault<NoHashHasher<u64>>>;
 
+pub(crate) fn make_symbol_map<'a>(symbols: impl IntoIterator<Item = &'a str>) -> SymbolMap {
+    symbols
+        .into_iter()
+        .map(|symbol| (fxhash::hash_bytes(symbol.as_bytes()), symbol.into()))
+        .collect()
+}
+
+// NOTE: field names are built lazily, the cell must be thread-safe only if
+// serializers can be shared across threads.
+#[cfg(not(feature = "send"))]
+pub(crate) type FieldNamesCell = std::cell::OnceCell<FieldNames>;
+#[cfg(feature = "send")]
+pub(crate) type FieldNamesCell = std::sync::OnceLock<FieldNames>;
+
+type FieldNameMap = HashMap<u64, Box<str>, BuildHasherDefault<NoHashHasher<u64>>>;
+
+// DynamicArrayNames describes names of items of a dynamic array. keys of items
+// can't be computed upfront because length of the array is only known at
+// runtime.
+#[derive(Debug, Clone, Default)]
+struct DynamicArrayNames {
+    key: u64,
+    name: Box<str>,
+    // hash parts (relative to the key of an item) and dotted names of item's
+    // fields; empty parts and name describe the item itself.
+    item: Vec<(Vec<u64>, Box<str>)>,
+}
+
+// FieldNames maps field keys to dotted paths (e.g.
+// "m_vecStatViewerModifierValues.0.m_flValue"); it is the reverse of
+// [`FlattenedSerializer::resolve_field_key`]. unlike
+// [`FlattenedSerializer::field_name`] it does not depend on preserve-metadata
+// feature.
+//
+// NOTE: dynamic arrays nested within items of other dynamic arrays are named
+// only up to their length field.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct FieldNames {
+    names: FieldNameMap,
+    dynamic_arrays: Vec<DynamicArrayNames>,
+}
+
+impl FieldNames {
+    pub(crate) fn new(serializer: &FlattenedSerializer, symbols: &SymbolMap) -> Self {
+        let mut names = FieldNameMap::default();
+        let mut dynamic_arrays = Vec::new();
+
+        for field in serializer.fields.iter() {
+            let Some(var_name) = symbols.get(&field.var_name.hash) else {
+                continue;
+            };
+            let mut parts = vec![field.var_name.hash];
+            let mut name = var_name.to_string();
+            visit_field_names(
+                field,
+                symbols,
+                &mut parts,
+                &mut name,
+                &mut |field, parts, name| {
+                    let key = parts[1..]
+                        .iter()
+                        .fold(parts[0], |hash, part| fxhash::add_u64_to_hash(hash, *part));
+                    names.insert(key, name.into());
+
+                    if !field.is_dynamic_array() {
+                        return;
+                    }
+                    let mut item = Vec::new();
+                    if let Some(item_field) = field.get_child(0) {
+                        visit_field_names(
+                            item_field,
+                            symbols,
+                            &mut Vec::new(),
+                            &mut String::new(),
+                            &mut |_, parts, name| item.push((parts.to_vec(), name.into())),
+                        );
+                    }
+                    dynamic_arrays.push(DynamicArrayNames {
+                        key,
+                        name: name.into(),
+                        item,
+                    });
+                },
+            );
+        }
+
+        Self {
+            names,
+            dynamic_arrays,
+        }
+    }
+
+    // get looks up dotted path of a field; get_len must return length of a
+    // dynamic array (which is the value of array's own field).
+    pub(crate) fn get(&self, key: u64, get_len: impl Fn(u64) -> Option<u64>) -> Option<String> {
+        if let Some(name) = self.names.get(&key) {
+            return Some(name.to_string());
+        }
+
+        for array in self.dynamic_arrays.iter() {
+            let Some(len) = get_len(array.key) else {
+                continue;
+            };
+            for index in 0..len {
+                let item_key =
+                    fxhash::add_u64_to_hash(array.key, fxhash::add_u64_to_hash(0, index));
+                for (parts, name) in array.item.iter() {
+                    let field_key = parts
+                        .iter()
+                        .fold(item_key, |hash, part| fxhash::add_u64_to_hash(hash, *part));
+                    if field_key == key {
+                        return Some(format!("{}.{}{}", array.name, index, name));
+                    }
+                }
+            }
+        }
+
+        None
+    }
+}
+
+// visit_field_names calls f with hash parts and dotted name of the field and of
+// all of its children the same way entities compute keys of decoded fields.
+// items of dynamic arrays are not visited.
+fn visit_field_names(
+    field: &FlattenedSerializerField,
+    symbols: &SymbolMap,
+    parts: &mut Vec<u64>,
+    name: &mut String,
+    f: &mut impl FnMut(&FlattenedSerializerField, &[u64], &str),
+) {
+    f(field, parts, name);
+
+    if field.is_dynamic_array() {
+        return;
+    }
+
+    let Some(field_serializer) = field.field_serializer.as_ref() else {
+        return;
+    };
+    let is_fixed_array = field.is_fixed_array();
+    for (index, child) in field_serializer.fields.iter().enumerate() {
+        let len = name.len();
+        if is_fixed_array {
+            parts.push(fxhash::add_u64_to_hash(0, index as u64));
+            name.push('.');
+            name.push_str(&index.to_string());
+        } else {
+            let Some(var_name) = symbols.get(&child.var_name.hash) else {
+                continue;
+            };
+            parts.push(child.var_name.hash);
+            name.push('.');
+            name.push_str(var_name);
+        }
+        visit_field_names(child, symbols, parts, name, f);
+        parts.pop();
+        name.truncate(len);
+    }
+}
+
+type FieldMap =
+    HashMap<i32, Shared<FlattenedSerializerField>, BuildHasherDefault<NoHashHasher<i32>>>;
+type SerializerMap =
+    HashMap<u64, Shared<FlattenedSerializer>, BuildHasherDefault<NoHashHasher<u64>>>;
+
+// NOTE: Clone is cheap-ish; serializers are behind Rcs. it is derived for
+// SerializerCache.
+#[derive(Clone)]
 pub struct FlattenedSerializerContainer {
     serializer_map: SerializerMap,
 }
@@ -256,10 +523,13 @@ impl FlattenedSerializerContainer {
             BuildHasherDefault::default(),
         );
 
+        let symbols = Shared::new(make_symbol_map(msg.symbols.iter().map(String::as_str)));
+
         // TODO: can fields be stored flatly?
 
         for serializer in msg.serializers.iter() {
-            let mut flattened_serializer = FlattenedSerializer::new(&msg, serializer)?;
+            let mut flattened_serializer =
+                FlattenedSerializer::new(&msg, serializer, Shared::clone(&symbols))?;
 
             for field_index in serializer.fields_index.iter() {
                 let field = if let Some(field) = fields.get(field_index) {
@@ -281,18 +551,23 @@ impl FlattenedSerializerContainer {
                     // TODO: maybe extract arms into separate functions
                     match field.metadata.special_descriptor {
                         Some(FieldSpecialDescriptor::FixedArray { length }) => {
-                            field.field_serializer = Some(Rc::new(FlattenedSerializer {
+                            // NOTE: items are copies of the array field, but
+                            // they are not arrays themselves (they may be
+                            // structs though).
+                            let mut item = field.clone();
+                            item.metadata.special_descriptor = None;
+                            field.field_serializer = Some(Shared::new(FlattenedSerializer {
                                 fields: {
                                     let mut fields = Vec::with_capacity(length);
-                                    fields.resize(length, Rc::new(field.clone()));
+                                    fields.resize(length, Shared::new(item));
                                     fields
                                 },
                                 ..Default::default()
                             }));
                         }
                         Some(FieldSpecialDescriptor::DynamicArray { ref decoder }) => {
-                            field.field_serializer = Some(Rc::new(FlattenedSerializer {
-                                fields: vec![Rc::new(FlattenedSerializerField {
+                            field.field_serializer = Some(Shared::new(FlattenedSerializer {
+                                fields: vec![Shared::new(FlattenedSerializerField {
                                     metadata: FieldMetadata {
                                         decoder: decoder.clone(),
                                         ..Default::default()
@@ -303,8 +578,8 @@ impl FlattenedSerializerContainer {
                             }));
                         }
                         Some(FieldSpecialDescriptor::DynamicSerializerArray) => {
-                            field.field_serializer = Some(Rc::new(FlattenedSerializer {
-                                fields: vec![Rc::new(FlattenedSerializerField {
+                            field.field_serializer = Some(Shared::new(FlattenedSerializer {
+                                fields: vec![Shared::new(FlattenedSerializerField {
                                     field_serializer: field
                                         .field_serializer_name
                                         .as_ref()
@@ -320,8 +595,8 @@ impl FlattenedSerializerContainer {
                         _ => {}
                     }
 
-                    let field = Rc::new(field);
-                    let ret = Rc::clone(&field);
+                    let field = Shared::new(field);
+                    let ret = Shared::clone(&field);
                     fields.insert(*field_index, field);
                     ret
                 };
@@ -330,17 +605,29 @@ impl FlattenedSerializerContainer {
 
             serializer_map.insert(
                 flattened_serializer.serializer_name.hash,
-                Rc::new(flattened_serializer),
+                Shared::new(flattened_serializer),
             );
         }
 
         Ok(Self { serializer_map })
     }
 
+    #[cfg(test)]
+    pub(crate) fn from_serializers(
+        serializers: impl IntoIterator<Item = Shared<FlattenedSerializer>>,
+    ) -> Self {
+        Self {
+            serializer_map: serializers
+                .into_iter()
+                .map(|serializer| (serializer.serializer_name.hash, serializer))
+                .collect(),
+        }
+    }
+
     // TODO: think about exposing the whole serializer map
 
     #[inline(always)]
-    pub fn by_name_hash(&self, serializer_name_hash: u64) -> Option<Rc<FlattenedSerializer>> {
+    pub fn by_name_hash(&self, serializer_name_hash: u64) -> Option<Shared<FlattenedSerializer>> {
         self.serializer_map.get(&serializer_name_hash).cloned()
     }
 
@@ -348,7 +635,7 @@ impl FlattenedSerializerContainer {
     pub unsafe fn by_name_hash_unckecked(
         &self,
         serializer_name_hash: u64,
-    ) -> Rc<FlattenedSerializer> {
+    ) -> Shared<FlattenedSerializer> {
         self.serializer_map
             .get(&serializer_name_hash)
             .unwrap_unchecked()
@@ -358,7 +645,280 @@ impl FlattenedSerializerContainer {
     }
 
     #[inline]
-    pub fn values(&self) -> Values<'_, u64, Rc<FlattenedSerializer>> {
+    pub fn values(&self) -> Values<'_, u64, Shared<FlattenedSerializer>> {
         self.serializer_map.values()
     }
 }
+
+// CachedContainer keeps send tables blob that the container was parsed from;
+// keys are 64-bit hashes that can collide, hits are confirmed by comparing
+// blobs.
+struct CachedContainer {
+    send_tables: Vec<u8>,
+    tick_interval: f32,
+    container: FlattenedSerializerContainer,
+}
+
+type ContainerMap = HashMap<u64, CachedContainer, BuildHasherDefault<NoHashHasher<u64>>>;
+
+// NOTE: cache needs to be synchronized only if it can be shared across
+// threads.
+#[cfg(not(feature = "send"))]
+type ContainerMapCell = std::cell::RefCell<ContainerMap>;
+#[cfg(feature = "send")]
+type ContainerMapCell = std::sync::Mutex<ContainerMap>;
+
+// SerializerCache allows to parse flattened serializers once and reuse them
+// across replays from the same game build; replays of the same build carry
+// identical send tables. the cache is keyed by a hash of send tables blob (and
+// tick interval which decoders depend on).
+//
+// NOTE: clones share the same cache; with send feature the cache can be shared
+// across threads.
+#[derive(Clone, Default)]
+pub struct SerializerCache {
+    containers: Shared<ContainerMapCell>,
+}
+
+impl SerializerCache {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // get_or_parse returns cached container if there's one for the send
+    // tables, otherwise parses and caches it.
+    pub fn get_or_parse(
+        &self,
+        cmd: CDemoSendTables,
+        ctx: FlattenedSerializerContext,
+    ) -> Result<FlattenedSerializerContainer> {
+        let key = make_cache_key(cmd.data(), &ctx);
+        if let Some(cached) = self.lock().get(&key) {
+            if cached.send_tables == cmd.data()
+                && cached.tick_interval.to_bits() == ctx.tick_interval.to_bits()
+            {
+                return Ok(cached.container.clone());
+            }
+        }
+
+        // NOTE: lock is not held while parsing; if several parsers stumble
+        // upon the same send tables simultaneously, each of them will parse.
+        let send_tables = cmd.data().to_vec();
+        let tick_interval = ctx.tick_interval;
+        let container = FlattenedSerializerContainer::parse(cmd, ctx)?;
+        self.lock().insert(
+            key,
+            CachedContainer {
+                send_tables,
+                tick_interval,
+                container: container.clone(),
+            },
+        );
+        Ok(container)
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    #[inline]
+    pub fn clear(&self) {
+        self.lock().clear();
+    }
+
+    // NOTE: borrows never outlive methods of the cache, thus borrow_mut can't
+    // fail.
+    #[cfg(not(feature = "send"))]
+    #[inline]
+    fn lock(&self) -> std::cell::RefMut<'_, ContainerMap> {
+        self.containers.borrow_mut()
+    }
+
+    // NOTE: poisoning is ignored; map can't be left in inconsistent state.
+    #[cfg(feature = "send")]
+    #[inline]
+    fn lock(&self) -> std::sync::MutexGuard<'_, ContainerMap> {
+        self.containers
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+}
+
+#[inline]
+fn make_cache_key(send_tables: &[u8], ctx: &FlattenedSerializerContext) -> u64 {
+    fxhash::add_u64_to_hash(
+        fxhash::hash_bytes(send_tables),
+        ctx.tick_interval.to_bits() as u64,
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::entities::make_field_key;
+
+    fn make_field(var_name: &str) -> FlattenedSerializerField {
+        FlattenedSerializerField {
+            var_name: Symbol::from(&var_name.to_owned()),
+            ..Default::default()
+        }
+    }
+
+    fn make_serializer(fields: Vec<FlattenedSerializerField>) -> Shared<FlattenedSerializer> {
+        Shared::new(FlattenedSerializer {
+            fields: fields.into_iter().map(Shared::new).collect(),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_resolve_field_key() {
+        let inner = make_serializer(vec![make_field("m_flValue")]);
+
+        let mut struct_field = make_field("m_pEntity");
+        struct_field.field_serializer = Some(inner.clone());
+
+        let mut vec_field = make_field("m_vecValues");
+        vec_field.metadata.special_descriptor =
+            Some(FieldSpecialDescriptor::DynamicSerializerArray);
+        vec_field.field_serializer = Some(make_serializer(vec![FlattenedSerializerField {
+            field_serializer: Some(inner),
+            ..Default::default()
+        }]));
+
+        let serializer = make_serializer(vec![struct_field, vec_field]);
+
+        assert_eq!(
+            serializer.resolve_field_key("m_pEntity.m_flValue"),
+            Some(make_field_key(&["m_pEntity", "m_flValue"]))
+        );
+        assert_eq!(
+            serializer.resolve_field_key("m_vecValues.3.m_flValue"),
+            Some(fxhash::add_u64_to_hash(
+                fxhash::add_u64_to_hash(
+                    fxhash::hash_bytes(b"m_vecValues"),
+                    fxhash::add_u64_to_hash(0, 3)
+                ),
+                fxhash::hash_bytes(b"m_flValue")
+            ))
+        );
+        assert_eq!(serializer.resolve_field_key("m_vecValues.x"), None);
+        assert_eq!(serializer.resolve_field_key("m_pEntity.0"), None);
+        assert_eq!(serializer.resolve_field_key("m_nope"), None);
+    }
+
+    #[test]
+    fn test_resolve_fixed_array_field_key() {
+        let mut array_field = make_field("m_iValues");
+        array_field.metadata.special_descriptor =
+            Some(FieldSpecialDescriptor::FixedArray { length: 3 });
+        let inner = make_serializer(vec![make_field("m_flValue")]);
+        let mut item = array_field.clone();
+        item.metadata.special_descriptor = None;
+        item.field_serializer = Some(inner);
+        let item = Shared::new(item);
+        array_field.field_serializer = Some(Shared::new(FlattenedSerializer {
+            fields: vec![item; 3],
+            ..Default::default()
+        }));
+
+        let serializer = make_serializer(vec![array_field]);
+
+        // NOTE: items of fixed arrays are keyed by index, not by their var
+        // names (that are the same as the name of the array).
+        let make_item_key = |index: u64| {
+            fxhash::add_u64_to_hash(
+                make_field_key(&["m_iValues"]),
+                fxhash::add_u64_to_hash(0, index),
+            )
+        };
+        assert_eq!(
+            serializer.resolve_field_key("m_iValues.0"),
+            Some(make_item_key(0))
+        );
+        assert_eq!(
+            serializer.resolve_field_key("m_iValues.2"),
+            Some(make_item_key(2))
+        );
+        assert_ne!(make_item_key(0), make_item_key(2));
+        // NOTE: items used to be keyed by var name of the array which made all
+        // of them share a single key; make sure that it's not the case
+        // anymore. indices are hashed as numbers, thus make_field_key can't
+        // build item keys.
+        assert_ne!(
+            serializer.resolve_field_key("m_iValues.0"),
+            Some(make_field_key(&["m_iValues", "m_iValues"]))
+        );
+        assert_ne!(
+            serializer.resolve_field_key("m_iValues.0"),
+            Some(make_field_key(&["m_iValues", "0"]))
+        );
+        // NOTE: items of fixed arrays can be structs.
+        assert_eq!(
+            serializer.resolve_field_key("m_iValues.1.m_flValue"),
+            Some(fxhash::add_u64_to_hash(
+                make_item_key(1),
+                fxhash::hash_bytes(b"m_flValue")
+            ))
+        );
+        assert_eq!(serializer.resolve_field_key("m_iValues.3"), None);
+        assert_eq!(serializer.resolve_field_key("m_iValues.m_iValues"), None);
+    }
+
+    #[test]
+    fn test_serializer_cache() -> Result<()> {
+        // NOTE: send tables blob is prefixed with size; this is an empty one.
+        let make_cmd = || CDemoSendTables {
+            data: Some(vec![0]),
+        };
+        let make_ctx = |tick_interval| FlattenedSerializerContext { tick_interval };
+
+        let cache = SerializerCache::new();
+        cache.get_or_parse(make_cmd(), make_ctx(1.0 / 30.0))?;
+        cache
+            .clone()
+            .get_or_parse(make_cmd(), make_ctx(1.0 / 30.0))?;
+        assert_eq!(cache.len(), 1);
+
+        cache.get_or_parse(make_cmd(), make_ctx(1.0 / 60.0))?;
+        assert_eq!(cache.len(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_serializer_cache_collision() -> Result<()> {
+        let cmd = CDemoSendTables {
+            data: Some(vec![0]),
+        };
+        let ctx = FlattenedSerializerContext {
+            tick_interval: 1.0 / 30.0,
+        };
+
+        // NOTE: pretend that another blob hashed to the same key.
+        let cache = SerializerCache::new();
+        cache.lock().insert(
+            make_cache_key(cmd.data(), &ctx),
+            CachedContainer {
+                send_tables: vec![1],
+                tick_interval: ctx.tick_interval,
+                container: FlattenedSerializerContainer::from_serializers([make_serializer(
+                    vec![],
+                )]),
+            },
+        );
+
+        let container = cache.get_or_parse(cmd, ctx)?;
+        assert_eq!(container.values().count(), 0);
+        assert_eq!(cache.len(), 1);
+
+        Ok(())
+    }
+}
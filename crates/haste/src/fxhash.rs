@@ -75,9 +75,22 @@ pub const fn hash_bytes(bytes: &[u8]) -> u64 {
 
     let mut i = 0;
 
-    let blocks: &[u64] = unsafe { core::mem::transmute(bytes) };
+    // NOTE: blocks are assembled byte by byte (instead of transmuting bytes
+    // into &[u64]) because bytes are not necessarily aligned to u64, const
+    // evaluation refuses to perform unaligned reads. compiler turns this into a
+    // single load anyway.
     while i < bytes.len() - (bytes.len() % size_of::<u64>()) {
-        hash = add_u64_to_hash(hash, blocks[i >> 3]);
+        let block = u64::from_ne_bytes([
+            bytes[i],
+            bytes[i + 1],
+            bytes[i + 2],
+            bytes[i + 3],
+            bytes[i + 4],
+            bytes[i + 5],
+            bytes[i + 6],
+            bytes[i + 7],
+        ]);
+        hash = add_u64_to_hash(hash, block);
         i += size_of::<u64>();
     }
 
@@ -88,3 +101,31 @@ pub const fn hash_bytes(bytes: &[u8]) -> u64 {
 
     hash
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // NOTE: values were produced by the previous implementation of hash_bytes
+    // (that transmuted bytes into &[u64]); field keys must not change. blocks
+    // are read in native byte order, so values are only valid on little-endian
+    // targets.
+    #[cfg(target_endian = "little")]
+    #[test]
+    fn test_hash_bytes() {
+        const M_I_SCORE: u64 = hash_bytes(b"m_iScore");
+
+        assert_eq!(hash_bytes(b""), 0);
+        assert_eq!(hash_bytes(b"m"), 0xb21e7afba97e8171);
+        assert_eq!(M_I_SCORE, 0x37eb3b84f188cc71);
+        assert_eq!(
+            hash_bytes(b"m_vecStatViewerModifierValues"),
+            0x46b57f2a6205962f
+        );
+
+        // unaligned
+        let buf = b"xm_iScorem_vecStatViewerModifierValues";
+        assert_eq!(hash_bytes(&buf[1..9]), M_I_SCORE);
+        assert_eq!(hash_bytes(&buf[9..]), 0x46b57f2a6205962f);
+    }
+}
@@ -0,0 +1,68 @@
+use crate::protos::{CDemoFileHeader, CDemoFileInfo};
+
+// Game is a game that recorded the replay.
+//
+// NOTE: detection does not depend on cargo features; features only decide
+// which game specific protos are compiled in (see haste_protos).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Game {
+    Dota2,
+    Deadlock,
+    Cs2,
+}
+
+impl Game {
+    // from_game_directory maps game directory (as in CDemoFileHeader, e.g.
+    // "/opt/srcds/dota/dota" or "citadel") onto a game.
+    pub fn from_game_directory(game_directory: &str) -> Option<Self> {
+        let dir = game_directory
+            .trim_end_matches(['/', '\\'])
+            .rsplit(['/', '\\'])
+            .next()
+            .unwrap_or_default();
+        match dir {
+            "dota" => Some(Self::Dota2),
+            "citadel" => Some(Self::Deadlock),
+            "csgo" => Some(Self::Cs2),
+            _ => None,
+        }
+    }
+
+    pub fn from_file_header(file_header: &CDemoFileHeader) -> Option<Self> {
+        Self::from_game_directory(file_header.game_directory())
+            .or_else(|| Self::from_game_directory(file_header.game()))
+    }
+
+    // from_file_info is a fallback for when file header is not available; it
+    // can tell apart only games that have game specific info (dota2 and cs2).
+    pub fn from_file_info(file_info: &CDemoFileInfo) -> Option<Self> {
+        let game_info = file_info.game_info.as_ref()?;
+        if game_info.dota.is_some() {
+            Some(Self::Dota2)
+        } else if game_info.cs.is_some() {
+            Some(Self::Cs2)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_from_game_directory() {
+        assert_eq!(
+            Game::from_game_directory("/opt/srcds/dota_v6000/dota"),
+            Some(Game::Dota2)
+        );
+        assert_eq!(Game::from_game_directory("citadel"), Some(Game::Deadlock));
+        assert_eq!(
+            Game::from_game_directory("C:\\cs2\\game\\csgo\\"),
+            Some(Game::Cs2)
+        );
+        assert_eq!(Game::from_game_directory("/home/hl2/hl2"), None);
+        assert_eq!(Game::from_game_directory(""), None);
+    }
+}
@@ -0,0 +1,358 @@
+use crate::protos::{
+    c_msg_source1_legacy_game_event, CMsgSource1LegacyGameEvent, CMsgSource1LegacyGameEventList,
+};
+use hashbrown::HashMap;
+use nohash::NoHashHasher;
+use std::hash::BuildHasherDefault;
+
+// NOTE: game events are legacy source 1 thing (see
+// https://developer.valvesoftware.com/wiki/Game_Events); in source 2 they are
+// sent as GE_Source1LegacyGameEventList (descriptors, sent once during signon)
+// and GE_Source1LegacyGameEvent (individual events) packets.
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    // mod
+    #[error("unknown game event id {0}")]
+    UnknownEventId(i32),
+    #[error("game event {name} has {got} keys, but descriptor has {want}")]
+    KeyCountMismatch {
+        name: Box<str>,
+        want: usize,
+        got: usize,
+    },
+    #[error("game event key {0} has no value")]
+    MissingKeyValue(Box<str>),
+    #[error("game event key {name} has unknown type {r#type}")]
+    UnknownKeyType { name: Box<str>, r#type: i32 },
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+// from game/shared/igameevents.h (source 1)
+//
+// #define TYPE_LOCAL   0 // not networked
+// #define TYPE_STRING  1 // zero terminated ASCII string
+// #define TYPE_FLOAT   2 // float 32 bit
+// #define TYPE_LONG    3 // signed int 32 bit
+// #define TYPE_SHORT   4 // signed int 16 bit
+// #define TYPE_BYTE    5 // unsigned int 8 bit
+// #define TYPE_BOOL    6 // unsigned int 1 bit
+// #define TYPE_UINT64  7 // unsigned int 64 bit
+pub const KEY_TYPE_STRING: i32 = 1;
+pub const KEY_TYPE_FLOAT: i32 = 2;
+pub const KEY_TYPE_LONG: i32 = 3;
+pub const KEY_TYPE_SHORT: i32 = 4;
+pub const KEY_TYPE_BYTE: i32 = 5;
+pub const KEY_TYPE_BOOL: i32 = 6;
+pub const KEY_TYPE_UINT64: i32 = 7;
+
+#[derive(Debug, Clone)]
+pub struct GameEventKey {
+    pub name: Box<str>,
+    pub r#type: i32,
+}
+
+#[derive(Debug, Clone)]
+pub struct GameEventDescriptor {
+    pub id: i32,
+    pub name: Box<str>,
+    pub keys: Vec<GameEventKey>,
+}
+
+impl GameEventDescriptor {
+    #[inline]
+    pub fn key_index(&self, name: &str) -> Option<usize> {
+        self.keys.iter().position(|key| key.name.as_ref().eq(name))
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct GameEventList {
+    descriptors: HashMap<i32, GameEventDescriptor, BuildHasherDefault<NoHashHasher<i32>>>,
+}
+
+impl GameEventList {
+    pub fn parse(msg: CMsgSource1LegacyGameEventList) -> Self {
+        let mut descriptors =
+            HashMap::with_capacity_and_hasher(msg.descriptors.len(), BuildHasherDefault::default());
+
+        for descriptor in msg.descriptors {
+            let id = descriptor.eventid();
+            descriptors.insert(
+                id,
+                GameEventDescriptor {
+                    id,
+                    name: descriptor.name().into(),
+                    keys: descriptor
+                        .keys
+                        .iter()
+                        .map(|key| GameEventKey {
+                            name: key.name().into(),
+                            r#type: key.r#type(),
+                        })
+                        .collect(),
+                },
+            );
+        }
+
+        Self { descriptors }
+    }
+
+    #[inline]
+    pub fn get(&self, id: i32) -> Option<&GameEventDescriptor> {
+        self.descriptors.get(&id)
+    }
+
+    pub fn find(&self, name: &str) -> Option<&GameEventDescriptor> {
+        self.descriptors
+            .values()
+            .find(|descriptor| descriptor.name.as_ref().eq(name))
+    }
+
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = &GameEventDescriptor> {
+        self.descriptors.values()
+    }
+
+    pub(crate) fn decode_event(&self, msg: CMsgSource1LegacyGameEvent) -> Result<GameEvent<'_>> {
+        let descriptor = self
+            .get(msg.eventid())
+            .ok_or(Error::UnknownEventId(msg.eventid()))?;
+
+        if descriptor.keys.len() != msg.keys.len() {
+            return Err(Error::KeyCountMismatch {
+                name: descriptor.name.clone(),
+                want: descriptor.keys.len(),
+                got: msg.keys.len(),
+            });
+        }
+
+        let values = descriptor
+            .keys
+            .iter()
+            .zip(msg.keys)
+            .map(|(key, value)| GameEventValue::from_key(key, value))
+            .collect::<Result<Vec<GameEventValue>>>()?;
+
+        Ok(GameEvent { descriptor, values })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum GameEventValue {
+    String(Box<str>),
+    Float(f32),
+    Long(i32),
+    Short(i16),
+    Byte(u8),
+    Bool(bool),
+    Uint64(u64),
+}
+
+impl GameEventValue {
+    fn from_key(
+        descriptor_key: &GameEventKey,
+        key: c_msg_source1_legacy_game_event::KeyT,
+    ) -> Result<Self> {
+        let value = match key.r#type() {
+            KEY_TYPE_STRING => key.val_string.map(|v| Self::String(v.into_boxed_str())),
+            KEY_TYPE_FLOAT => key.val_float.map(Self::Float),
+            KEY_TYPE_LONG => key.val_long.map(Self::Long),
+            KEY_TYPE_SHORT => key.val_short.map(|v| Self::Short(v as i16)),
+            KEY_TYPE_BYTE => key.val_byte.map(|v| Self::Byte(v as u8)),
+            KEY_TYPE_BOOL => key.val_bool.map(Self::Bool),
+            KEY_TYPE_UINT64 => key.val_uint64.map(Self::Uint64),
+            // NOTE: guessing the type from whichever val_* field is set would
+            // silently produce wrong values.
+            r#type => {
+                return Err(Error::UnknownKeyType {
+                    name: descriptor_key.name.clone(),
+                    r#type,
+                })
+            }
+        };
+        value.ok_or_else(|| Error::MissingKeyValue(descriptor_key.name.clone()))
+    }
+
+    #[inline]
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(value) => Some(value.as_ref()),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    pub fn as_f32(&self) -> Option<f32> {
+        match self {
+            Self::Float(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    // NOTE: as_i32 widens all signed/unsigned integer types that fit into i32.
+    #[inline]
+    pub fn as_i32(&self) -> Option<i32> {
+        match self {
+            Self::Long(value) => Some(*value),
+            Self::Short(value) => Some(*value as i32),
+            Self::Byte(value) => Some(*value as i32),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    pub fn as_u64(&self) -> Option<u64> {
+        match self {
+            Self::Uint64(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Self::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct GameEvent<'a> {
+    descriptor: &'a GameEventDescriptor,
+    values: Vec<GameEventValue>,
+}
+
+impl<'a> GameEvent<'a> {
+    #[inline]
+    pub fn id(&self) -> i32 {
+        self.descriptor.id
+    }
+
+    #[inline]
+    pub fn name(&self) -> &'a str {
+        self.descriptor.name.as_ref()
+    }
+
+    #[inline]
+    pub fn descriptor(&self) -> &'a GameEventDescriptor {
+        self.descriptor
+    }
+
+    // NOTE: get performs a linear scan over the keys of descriptor; events
+    // usually have only a handful of keys. if you need to look up the same
+    // key a lot - resolve its index once with
+    // [`GameEventDescriptor::key_index`] and use [`Self::get_by_index`].
+    #[inline]
+    pub fn get(&self, name: &str) -> Option<&GameEventValue> {
+        self.descriptor
+            .key_index(name)
+            .and_then(|i| self.values.get(i))
+    }
+
+    #[inline]
+    pub fn get_by_index(&self, index: usize) -> Option<&GameEventValue> {
+        self.values.get(index)
+    }
+
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = (&'a str, &GameEventValue)> {
+        self.descriptor
+            .keys
+            .iter()
+            .map(|key| key.name.as_ref())
+            .zip(self.values.iter())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::protos::c_msg_source1_legacy_game_event_list;
+
+    fn make_list() -> GameEventList {
+        GameEventList::parse(CMsgSource1LegacyGameEventList {
+            descriptors: vec![c_msg_source1_legacy_game_event_list::DescriptorT {
+                eventid: Some(42),
+                name: Some("dota_player_kill".to_string()),
+                keys: vec![
+                    c_msg_source1_legacy_game_event_list::KeyT {
+                        r#type: Some(KEY_TYPE_SHORT),
+                        name: Some("victim_userid".to_string()),
+                    },
+                    c_msg_source1_legacy_game_event_list::KeyT {
+                        r#type: Some(KEY_TYPE_BOOL),
+                        name: Some("first_blood".to_string()),
+                    },
+                ],
+            }],
+        })
+    }
+
+    #[test]
+    fn test_decode_event() -> Result<()> {
+        let list = make_list();
+        let event = list.decode_event(CMsgSource1LegacyGameEvent {
+            eventid: Some(42),
+            keys: vec![
+                c_msg_source1_legacy_game_event::KeyT {
+                    r#type: Some(KEY_TYPE_SHORT),
+                    val_short: Some(7),
+                    ..Default::default()
+                },
+                c_msg_source1_legacy_game_event::KeyT {
+                    r#type: Some(KEY_TYPE_BOOL),
+                    val_bool: Some(true),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        })?;
+
+        assert_eq!(event.name(), "dota_player_kill");
+        assert_eq!(event.get("victim_userid"), Some(&GameEventValue::Short(7)));
+        assert_eq!(
+            event.get("first_blood").and_then(|v| v.as_bool()),
+            Some(true)
+        );
+        assert_eq!(event.get("nonexistent"), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_unknown_event() {
+        let list = make_list();
+        let result = list.decode_event(CMsgSource1LegacyGameEvent {
+            eventid: Some(1),
+            ..Default::default()
+        });
+        assert!(matches!(result, Err(Error::UnknownEventId(1))));
+    }
+
+    #[test]
+    fn test_decode_unknown_key_type() {
+        let list = make_list();
+        let result = list.decode_event(CMsgSource1LegacyGameEvent {
+            eventid: Some(42),
+            keys: vec![
+                c_msg_source1_legacy_game_event::KeyT {
+                    r#type: Some(8),
+                    val_short: Some(7),
+                    ..Default::default()
+                },
+                c_msg_source1_legacy_game_event::KeyT {
+                    r#type: Some(KEY_TYPE_BOOL),
+                    val_bool: Some(true),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        });
+        assert!(matches!(
+            result,
+            Err(Error::UnknownKeyType { r#type: 8, .. })
+        ));
+    }
+}
@@ -1,11 +1,17 @@
-use crate::stringtables::StringTable;
-use std::{cell::UnsafeCell, rc::Rc};
+use crate::{stringtables::StringTable, Shared};
 
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
     // std
     #[error(transparent)]
     ParseIntError(#[from] std::num::ParseIntError),
+    #[error(transparent)]
+    Utf8Error(#[from] std::str::Utf8Error),
+    // mod
+    #[error("instance baseline item {0} has no class id")]
+    MissingClassId(i32),
+    #[error("class id {class_id} is out of bounds (classes: {classes})")]
+    InvalidClassId { class_id: i32, classes: usize },
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
@@ -15,7 +21,7 @@ pub const INSTANCE_BASELINE_TABLE_NAME: &str = "instancebaseline";
 #[derive(Default)]
 pub struct InstanceBaseline {
     // TODO: ref into StringTables instead of cloning
-    data: Vec<Option<Rc<UnsafeCell<Vec<u8>>>>>,
+    data: Vec<Option<Shared<Vec<u8>>>>,
 }
 
 impl InstanceBaseline {
@@ -24,27 +30,44 @@ impl InstanceBaseline {
             self.data.resize(classes, None);
         }
 
-        for (_entity_index, item) in string_table.items() {
+        for (item_index, item) in string_table.items() {
+            let bytes = item
+                .string
+                .as_ref()
+                .ok_or(Error::MissingClassId(*item_index))?;
             // SAFETY: in normal circumbstances this is safe; it is expected for
             // instancebaseline's string to be convertable to number, if it
             // cannot be converted to number - fail loudly!
-            let string =
-                unsafe { std::str::from_utf8_unchecked(item.string.as_ref().unwrap_unchecked()) };
+            #[cfg(not(feature = "safe"))]
+            let string = unsafe { std::str::from_utf8_unchecked(bytes) };
+            #[cfg(feature = "safe")]
+            let string = std::str::from_utf8(bytes)?;
             let class_id = string.parse::<i32>()?;
-            self.data[class_id as usize] = item.user_data.clone();
+            let classes = self.data.len();
+            let data = usize::try_from(class_id)
+                .ok()
+                .and_then(|index| self.data.get_mut(index))
+                .ok_or(Error::InvalidClassId { class_id, classes })?;
+            *data = item.user_data.clone();
         }
         Ok(())
     }
 
+    #[inline]
+    pub fn by_id(&self, class_id: i32) -> Option<&[u8]> {
+        self.data
+            .get(class_id as usize)
+            .and_then(|v| v.as_ref())
+            .map(|v| v.as_slice())
+    }
+
     #[inline]
     pub unsafe fn by_id_unchecked(&self, class_id: i32) -> &[u8] {
         unsafe {
-            &*self
-                .data
+            self.data
                 .get_unchecked(class_id as usize)
                 .as_ref()
                 .unwrap_unchecked()
-                .get()
         }
     }
 
@@ -54,3 +77,46 @@ impl InstanceBaseline {
         self.data.clear();
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::protos::c_demo_string_tables::{ItemsT, TableT};
+
+    fn make_string_table(strings: &[&str]) -> StringTable {
+        let mut string_table =
+            StringTable::new(INSTANCE_BASELINE_TABLE_NAME, false, 0, 0, 0, false);
+        string_table.do_full_update(&TableT {
+            table_name: Some(INSTANCE_BASELINE_TABLE_NAME.to_string()),
+            items: strings
+                .iter()
+                .map(|string| ItemsT {
+                    str: Some(string.to_string()),
+                    data: Some(string.as_bytes().to_vec()),
+                })
+                .collect(),
+            ..Default::default()
+        });
+        string_table
+    }
+
+    #[test]
+    fn test_update() -> Result<()> {
+        let mut instance_baseline = InstanceBaseline::default();
+        instance_baseline.update(&make_string_table(&["1", "0"]), 2)?;
+        assert_eq!(instance_baseline.by_id(0), Some(b"0".as_slice()));
+        assert_eq!(instance_baseline.by_id(1), Some(b"1".as_slice()));
+        assert_eq!(instance_baseline.by_id(2), None);
+
+        // NOTE: malformed class ids must not panic.
+        for class_id in ["2", "-1"] {
+            let mut instance_baseline = InstanceBaseline::default();
+            assert!(matches!(
+                instance_baseline.update(&make_string_table(&[class_id]), 2),
+                Err(Error::InvalidClassId { classes: 2, .. })
+            ));
+        }
+
+        Ok(())
+    }
+}
@@ -3,16 +3,29 @@
 #![deny(clippy::panic)]
 
 // TODO: figure pub scopes for all the things
+#[cfg(feature = "async")]
+pub mod asyncdemofile;
+#[cfg(feature = "async")]
+pub mod asyncparser;
+pub mod batch;
 pub(crate) mod bitbuf;
+pub mod broadcast;
+#[cfg(feature = "broadcast-client")]
+pub mod broadcastclient;
 pub mod demofile;
+pub mod demoindex;
+pub mod demosource;
 pub mod entities;
 pub mod entityclasses;
+pub mod error;
 pub mod fielddecoder; // TODO: try to not publicly expose fielddecoder
 pub mod fieldmetadata; // TODO: try to not publicly expose fieldmetadata
 pub mod fieldpath;
 pub mod fieldvalue;
 pub mod flattenedserializers;
 pub mod fxhash;
+pub mod game;
+pub mod gameevents;
 pub mod instancebaseline;
 pub mod parser;
 pub mod quantizedfloat; // TODO: try to not publicly expose quantizedfloat
@@ -22,6 +35,20 @@ pub mod stringtables;
 pub use haste_protos as protos;
 pub(crate) use haste_vartype as vartype;
 
+// NOTE: with send feature state that is shared within parser (flattened
+// serializers, string table user data) is Arc-based which makes Parser Send.
+#[cfg(not(feature = "send"))]
+pub(crate) type Shared<T> = std::rc::Rc<T>;
+#[cfg(feature = "send")]
+pub(crate) type Shared<T> = std::sync::Arc<T>;
+
+pub use demosource::{open, open_with_visitor};
+pub use error::{Error, Result};
+pub use game::Game;
+
+#[cfg(feature = "derive")]
+pub use haste_derive::EntityView;
+
 // TOOD: more optimizations, specifically look into
 // https://agourlay.github.io/rust-performance-retrospective-part2/
 
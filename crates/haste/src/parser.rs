@@ -1,18 +1,32 @@
 use crate::{
     bitbuf::BitReader,
-    demofile::{CmdHeader, DemoFile, DemoHeader, DEMO_BUFFER_SIZE, DEMO_HEADER_SIZE},
-    entities::{self, EntityContainer},
+    demofile::{
+        CmdHeader, DemoFile, DemoHeader, DemoRead, DemoSeek, DEMO_BUFFER_SIZE, DEMO_HEADER_SIZE,
+    },
+    demoindex::{DemoIndex, FullPacketEntry},
+    entities::{self, Entity, EntityContainer, UpdateType},
     entityclasses::EntityClasses,
-    flattenedserializers::{FlattenedSerializerContainer, FlattenedSerializerContext},
+    error::ErrorContext,
+    flattenedserializers::{
+        FlattenedSerializerContainer, FlattenedSerializerContext, SerializerCache,
+    },
+    game::Game,
+    gameevents::{self, GameEvent, GameEventList},
     instancebaseline::{InstanceBaseline, INSTANCE_BASELINE_TABLE_NAME},
     protos::{
-        prost::Message, CDemoClassInfo, CDemoFileInfo, CDemoFullPacket, CDemoPacket,
-        CDemoSendTables, CDemoStringTables, CsvcMsgCreateStringTable, CsvcMsgPacketEntities,
-        CsvcMsgServerInfo, CsvcMsgUpdateStringTable, EDemoCommands, SvcMessages,
+        prost::Message, CDemoClassInfo, CDemoFileHeader, CDemoFileInfo, CDemoFullPacket,
+        CDemoPacket, CDemoSendTables, CDemoStringTables, CMsgSource1LegacyGameEvent,
+        CMsgSource1LegacyGameEventList, CsvcMsgCreateStringTable, CsvcMsgPacketEntities,
+        CsvcMsgServerInfo, CsvcMsgUpdateStringTable, EBaseGameEvents, EDemoCommands, SvcMessages,
     },
-    stringtables::StringTableContainer,
+    stringtables::{StringTableContainer, StringTableSnapshot},
+};
+use std::{
+    collections::{BTreeMap, VecDeque},
+    io::SeekFrom,
+    num::NonZeroU32,
+    ops::Range,
 };
-use std::io::{Read, Seek, SeekFrom};
 
 // as can be observed when dumping commands. also as specified in clarity
 // (src/main/java/skadistats/clarity/model/engine/AbstractDotaEngineType.java)
@@ -25,8 +39,7 @@ const DEFAULT_FULL_PACKET_INTERVAL: i32 = 1800;
 // dota2's tick interval is 1 / 30; deadlock's 1 / 60 - they are constant.
 const DEFAULT_TICK_INTERVAL: f32 = 1.0 / 30.0;
 
-pub type Error = Box<dyn std::error::Error>;
-pub type Result<T> = std::result::Result<T, Error>;
+pub use crate::error::{Error, Result};
 
 // NOTE: primary purpose of Context is to to be able to expose state to the
 // public; attempts to put parser into arguments of Visitor's method did not
@@ -37,6 +50,8 @@ pub struct Context {
     serializers: Option<FlattenedSerializerContainer>,
     entity_classes: Option<EntityClasses>,
     entities: EntityContainer,
+    game_event_list: Option<GameEventList>,
+    game: Option<Game>,
     tick: i32,
     prev_tick: i32,
     tick_interval: f32,
@@ -73,6 +88,37 @@ impl Context {
             Some(&self.entities)
         }
     }
+
+    #[inline]
+    pub fn game_event_list(&self) -> Option<&GameEventList> {
+        self.game_event_list.as_ref()
+    }
+
+    // game is detected from DemFileHeader cmd, it is none until the cmd is
+    // handled (or if the game is not recognized).
+    #[inline]
+    pub fn game(&self) -> Option<Game> {
+        self.game
+    }
+}
+
+// NOTE: serialized context contains only the state that can be observed
+// through entities and string tables; serializers, entity classes, etc. are
+// omitted.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Context {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        let mut state = serializer.serialize_struct("Context", 3)?;
+        state.serialize_field("tick", &self.tick)?;
+        state.serialize_field("string_tables", &self.string_tables)?;
+        state.serialize_field("entities", &self.entities)?;
+        state.end()
+    }
 }
 
 pub trait Visitor {
@@ -82,8 +128,9 @@ pub trait Visitor {
         ctx: &Context,
         update_flags: usize,
         update_type: entities::UpdateType,
-        // TODO: include updated fields (list of field paths?)
         entity: &entities::Entity,
+        // NOTE: changes is empty when entity is being deleted.
+        changes: &[entities::FieldChange],
     ) -> Result<()> {
         Ok(())
     }
@@ -98,6 +145,19 @@ pub trait Visitor {
         Ok(())
     }
 
+    #[allow(unused_variables)]
+    fn on_game_event(&mut self, ctx: &Context, event: &GameEvent) -> Result<()> {
+        Ok(())
+    }
+
+    // on_game_event_error is called instead of on_game_event when an event
+    // can't be decoded (e.g. it has keys of unknown types); such events are
+    // skipped, return an error to abort parsing instead.
+    #[allow(unused_variables)]
+    fn on_game_event_error(&mut self, ctx: &Context, err: &gameevents::Error) -> Result<()> {
+        Ok(())
+    }
+
     // TODO: come up with an example that would use / will rely on on_tick_end
     #[allow(unused_variables)]
     fn on_tick_end(&mut self, ctx: &Context) -> Result<()> {
@@ -122,20 +182,103 @@ pub enum ControlFlow {
     Break,
 }
 
+// TailStatus indicates why [`Parser::run_tail`] returned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TailStatus {
+    // WouldBlock indicates that there's not enough data to continue; the
+    // stream is positioned at the beginning of the incomplete cmd.
+    WouldBlock,
+    // Stop indicates that DemStop cmd was reached, the recording is complete.
+    Stop,
+    // Break indicates that handler returned ControlFlow::Break.
+    Break,
+}
+
+// Snapshot is a copy of the mutable parts of Context; snapshots allow to seek
+// backwards without re-running the demo from the beginning (or from the closest
+// full packet).
+//
+// NOTE: flattened serializers, entity classes and game event list are not
+// included because they never change after signon.
+struct Snapshot {
+    // offset is a position of the next cmd header
+    offset: u64,
+    string_tables: Vec<StringTableSnapshot>,
+    entities: EntityContainer,
+}
+
+// DemoCmd is a decoded cmd. decoding is separated from handling so that cmd
+// data does not need to outlive decoding.
+enum DemoCmd {
+    // NOTE: game is the only thing that parser takes from file header.
+    FileHeader(Option<Game>),
+    Packet(CDemoPacket),
+    SendTables(CDemoSendTables),
+    ClassInfo(CDemoClassInfo),
+    // Ignored is a cmd that parser does not care about (or does not need to
+    // handle again).
+    Ignored,
+}
+
+impl DemoCmd {
+    fn decode(ctx: &Context, command: EDemoCommands, data: &[u8]) -> Result<Self> {
+        let cmd = match command {
+            EDemoCommands::DemFileHeader => {
+                Self::FileHeader(Game::from_file_header(&CDemoFileHeader::decode(data)?))
+            }
+
+            EDemoCommands::DemPacket | EDemoCommands::DemSignonPacket => {
+                Self::Packet(CDemoPacket::decode(data)?)
+            }
+
+            // NOTE: this check exists because seeking exists, there's no need
+            // to re-parse flattened serializers
+            EDemoCommands::DemSendTables if ctx.serializers.is_none() => {
+                Self::SendTables(CDemoSendTables::decode(data)?)
+            }
+
+            // NOTE: this check exists because seeking exists, there's no need
+            // to re-parse entity classes
+            EDemoCommands::DemClassInfo if ctx.entity_classes.is_none() => {
+                Self::ClassInfo(CDemoClassInfo::decode(data)?)
+            }
+
+            _ => Self::Ignored,
+        };
+        Ok(cmd)
+    }
+}
+
 // TODO: maybe rename to DemoPlayer (or DemoRunner?)
-pub struct Parser<R: Read + Seek, V: Visitor> {
+pub struct Parser<R: DemoRead, V: Visitor> {
     demo_file: DemoFile<R>,
     buf: Vec<u8>,
     visitor: V,
     ctx: Context,
+    // packet_type is the type of a message within a packet that is currently
+    // being handled; is used only to provide context for errors.
+    packet_type: Option<u32>,
+    // tail_len is a cached stream length that is used by run_tail.
+    tail_len: u64,
+    // pending_cmd_header is a cmd header that run_stream could not "unread".
+    pending_cmd_header: Option<CmdHeader>,
+    index: Option<DemoIndex>,
+    snapshot_interval: Option<i32>,
+    snapshots: BTreeMap<i32, Snapshot>,
+    serializer_cache: Option<SerializerCache>,
+    // event_queue collects events while [`Events`] is alive.
+    event_queue: Option<EventQueue>,
 }
 
-impl<R: Read + Seek, V: Visitor> Parser<R, V> {
+impl<R: DemoRead, V: Visitor> Parser<R, V> {
     pub fn from_reader_with_visitor(rdr: R, visitor: V) -> Result<Self> {
         let mut demo_file = DemoFile::from_reader(rdr);
         let _demo_header = demo_file.read_demo_header()?;
+        Ok(Self::from_demo_file_with_visitor(demo_file, visitor))
+    }
 
-        Ok(Self {
+    fn from_demo_file_with_visitor(demo_file: DemoFile<R>, visitor: V) -> Self {
+        Self {
             demo_file,
             buf: vec![0; DEMO_BUFFER_SIZE],
             visitor,
@@ -145,14 +288,131 @@ impl<R: Read + Seek, V: Visitor> Parser<R, V> {
                 instance_baseline: InstanceBaseline::default(),
                 serializers: None,
                 entity_classes: None,
+                game_event_list: None,
+                game: None,
                 tick: -1,
                 prev_tick: -1,
                 tick_interval: DEFAULT_TICK_INTERVAL,
                 full_packet_interval: DEFAULT_FULL_PACKET_INTERVAL,
             },
-        })
+            packet_type: None,
+            tail_len: 0,
+            pending_cmd_header: None,
+            index: None,
+            snapshot_interval: None,
+            snapshots: BTreeMap::new(),
+            serializer_cache: None,
+            event_queue: None,
+        }
+    }
+
+    // ----
+
+    // run_stream is a variant of [`Self::run`] that does not require the
+    // reader to be seekable, it allows to parse replays from decompressing
+    // streams, stdin, etc.
+    //
+    // NOTE: SkipCmd reads and discards the cmd; Break keeps the cmd header
+    // around and the next run_stream call starts with it.
+    pub fn run_stream<F>(&mut self, mut handler: F) -> Result<()>
+    where
+        F: FnMut(&mut Self, &CmdHeader) -> Result<ControlFlow>,
+    {
+        loop {
+            let Some(cmd_header) = self.next_cmd_header()? else {
+                return Ok(());
+            };
+
+            self.ctx.prev_tick = self.ctx.tick;
+            self.ctx.tick = cmd_header.tick;
+            match handler(self, &cmd_header)? {
+                ControlFlow::HandleCmd => {
+                    self.handle_cmd(&cmd_header)
+                        .map_err(|err| self.wrap_err_at(&cmd_header, err, None))?;
+                    if self.ctx.prev_tick != self.ctx.tick {
+                        self.visitor.on_tick_end(&self.ctx)?;
+                    }
+                }
+                ControlFlow::SkipCmd => self.demo_file.discard_cmd(&cmd_header)?,
+                ControlFlow::IgnoreCmd => {}
+                ControlFlow::Break => {
+                    self.pending_cmd_header = Some(cmd_header);
+                    self.ctx.tick = self.ctx.prev_tick;
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    pub fn run_stream_to_end(&mut self) -> Result<()> {
+        self.run_stream(|_notnotself, _cmd_header| Ok(ControlFlow::HandleCmd))
+    }
+
+    // next_cmd_header returns the cmd header that run_stream could not
+    // "unread" or reads the next one; returns none at the end of the stream.
+    fn next_cmd_header(&mut self) -> Result<Option<CmdHeader>> {
+        match self.pending_cmd_header.take() {
+            Some(cmd_header) => Ok(Some(cmd_header)),
+            None => self.demo_file.try_read_cmd_header().map_err(Error::from),
+        }
+    }
+}
+
+// NOTE: methods below exist for [`crate::broadcast::BroadcastParser`] and
+// [`crate::asyncparser::AsyncParser`]. broadcast fragments carry the same cmds
+// as demo files, but there's no demo header nor file info, thus parsers that
+// are constructed for fragments must not leak out.
+impl<R: DemoRead, V: Visitor> Parser<R, V> {
+    pub(crate) fn from_fragment_reader_with_visitor(rdr: R, visitor: V) -> Self {
+        Self::from_demo_file_with_visitor(DemoFile::from_fragment_reader(rdr), visitor)
+    }
+
+    #[inline]
+    pub(crate) fn fragment_reader_mut(&mut self) -> &mut R {
+        self.demo_file.get_mut()
     }
 
+    // run_fragment handles all cmds of a fragment. full packets are ignored
+    // during regular runs; with handle_full_packets they replace entities.
+    pub(crate) fn run_fragment(&mut self, handle_full_packets: bool) -> Result<()> {
+        while let Some(cmd_header) = self.demo_file.try_read_cmd_header()? {
+            self.handle_fragment_cmd(&cmd_header, handle_full_packets)?;
+        }
+        Ok(())
+    }
+
+    // handle_fragment_cmd handles a single cmd whose header was read elsewhere;
+    // cmd data is expected to be next in fragment reader.
+    pub(crate) fn handle_fragment_cmd(
+        &mut self,
+        cmd_header: &CmdHeader,
+        handle_full_packets: bool,
+    ) -> Result<()> {
+        self.ctx.prev_tick = self.ctx.tick;
+        self.ctx.tick = cmd_header.tick;
+
+        if handle_full_packets && cmd_header.command == EDemoCommands::DemFullPacket {
+            let cmd_data = self.demo_file.read_cmd(cmd_header)?;
+            self.visitor.on_cmd(&self.ctx, cmd_header, cmd_data)?;
+
+            let cmd = CDemoFullPacket::decode(cmd_data)?;
+            self.ctx.entities.clear();
+            self.handle_cmd_full_packet(cmd)
+                .map_err(|err| self.wrap_err_at(cmd_header, err, None))?;
+        } else {
+            self.handle_cmd(cmd_header)
+                .map_err(|err| self.wrap_err_at(cmd_header, err, None))?;
+        }
+
+        if self.ctx.prev_tick != self.ctx.tick {
+            self.visitor.on_tick_end(&self.ctx)?;
+        }
+        Ok(())
+    }
+}
+
+// NOTE: methods below require the reader to be seekable.
+impl<R: DemoSeek, V: Visitor> Parser<R, V> {
     // ----
 
     pub fn run<F>(&mut self, mut handler: F) -> Result<()>
@@ -164,23 +424,9 @@ impl<R: Read + Seek, V: Visitor> Parser<R, V> {
                 Ok(cmd_header) => {
                     self.ctx.prev_tick = self.ctx.tick;
                     self.ctx.tick = cmd_header.tick;
-                    match handler(self, &cmd_header) {
-                        Ok(cf) => match cf {
-                            ControlFlow::HandleCmd => {
-                                self.handle_cmd(&cmd_header)?;
-                                if self.ctx.prev_tick != self.ctx.tick {
-                                    self.visitor.on_tick_end(&self.ctx)?;
-                                }
-                            }
-                            ControlFlow::SkipCmd => self.demo_file.skip_cmd(&cmd_header)?,
-                            ControlFlow::IgnoreCmd => {}
-                            ControlFlow::Break => {
-                                self.demo_file.unread_cmd_header(&cmd_header)?;
-                                self.ctx.tick = self.ctx.prev_tick;
-                                return Ok(());
-                            }
-                        },
-                        Err(err) => return Err(Error::from(err)),
+                    let cf = handler(self, &cmd_header)?;
+                    if !self.apply_control_flow(&cmd_header, cf)? {
+                        return Ok(());
                     }
                 }
                 Err(err) => {
@@ -193,10 +439,118 @@ impl<R: Read + Seek, V: Visitor> Parser<R, V> {
         }
     }
 
+    // run_tail is similar to [`Self::run`], but it is meant to be used with
+    // demo files that are still being written (e.g. by tv_record). when the end
+    // of the file is reached or the last cmd is incomplete it rewinds to the
+    // last complete cmd header and returns [`TailStatus::WouldBlock`]; call it
+    // again once more bytes were appended, all the state is preserved. corrupt
+    // (or unknown) cmd headers result in errors once all of their bytes are
+    // available.
+    //
+    // NOTE: handler is called only for cmds that are fully available.
+    pub fn run_tail<F>(&mut self, mut handler: F) -> Result<TailStatus>
+    where
+        F: FnMut(&mut Self, &CmdHeader) -> Result<ControlFlow>,
+    {
+        loop {
+            // NOTE: there's no cheaper way to know where the cmd starts;
+            // IgnoreCmd allows handler to read stuff on its own.
+            let offset = self.demo_file.stream_position()?;
+
+            let cmd_header = match self.demo_file.read_cmd_header() {
+                Ok(cmd_header) => cmd_header,
+                Err(err) => {
+                    self.demo_file.seek(SeekFrom::Start(offset))?;
+                    if self.demo_file.has_cmd_header_bytes()? {
+                        return Err(Error::from(err));
+                    }
+                    self.tail_len = self.demo_file.stream_len()?;
+                    return Ok(TailStatus::WouldBlock);
+                }
+            };
+
+            // NOTE: stream_len is cached because seeking to the end discards
+            // buffers of buffered readers; it is refreshed only when it's too
+            // small.
+            let end = offset + cmd_header.bytes_read as u64 + cmd_header.size as u64;
+            if end > self.tail_len {
+                self.tail_len = self.demo_file.stream_len()?;
+                if end > self.tail_len {
+                    self.demo_file.seek(SeekFrom::Start(offset))?;
+                    return Ok(TailStatus::WouldBlock);
+                }
+            }
+
+            self.ctx.prev_tick = self.ctx.tick;
+            self.ctx.tick = cmd_header.tick;
+            let cf = handler(self, &cmd_header)?;
+            if !self.apply_control_flow(&cmd_header, cf)? {
+                return Ok(TailStatus::Break);
+            }
+            if cmd_header.command == EDemoCommands::DemStop {
+                return Ok(TailStatus::Stop);
+            }
+        }
+    }
+
+    pub fn run_tail_to_end(&mut self) -> Result<TailStatus> {
+        self.run_tail(|_notnotself, _cmd_header| Ok(ControlFlow::HandleCmd))
+    }
+
+    // apply_control_flow returns false if the run loop needs to stop.
+    fn apply_control_flow(&mut self, cmd_header: &CmdHeader, cf: ControlFlow) -> Result<bool> {
+        match cf {
+            ControlFlow::HandleCmd => {
+                self.handle_cmd(cmd_header)
+                    .map_err(|err| self.wrap_err(cmd_header, err))?;
+                if self.ctx.prev_tick != self.ctx.tick {
+                    self.visitor.on_tick_end(&self.ctx)?;
+                    self.maybe_take_snapshot()?;
+                }
+            }
+            ControlFlow::SkipCmd => self.demo_file.skip_cmd(cmd_header)?,
+            ControlFlow::IgnoreCmd => {}
+            ControlFlow::Break => {
+                self.demo_file.unread_cmd_header(cmd_header)?;
+                self.ctx.tick = self.ctx.prev_tick;
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
     pub fn run_to_end(&mut self) -> Result<()> {
         self.run(|_notnotself, _cmd_header| Ok(ControlFlow::HandleCmd))
     }
 
+    // commands returns a lending iterator over cmds; each cmd is handled
+    // before it is yielded, thus context reflects the cmd. see [`Commands`].
+    //
+    // NOTE: cmds are handled the same way as in run (snapshots are taken),
+    // that is why a seekable reader is required; use run_stream for streams.
+    pub fn commands(&mut self) -> Commands<'_, R, V> {
+        Commands { parser: self }
+    }
+
+    // events returns a lending iterator over entity updates, packets and tick
+    // boundaries. see [`Events`].
+    pub fn events(&mut self) -> Events<'_, R, V> {
+        self.event_queue = Some(EventQueue::default());
+        Events {
+            parser: self,
+            tick_end: None,
+        }
+    }
+
+    // step handles the cmd the same way as [`Self::run`] does (which includes
+    // taking snapshots).
+    fn step(&mut self, cmd_header: &CmdHeader) -> Result<()> {
+        self.ctx.prev_tick = self.ctx.tick;
+        self.ctx.tick = cmd_header.tick;
+        self.apply_control_flow(cmd_header, ControlFlow::HandleCmd)
+            .map(|_| ())
+    }
+
     // TODO: this probably has to be private?
     pub fn reset(&mut self) -> Result<()> {
         self.demo_file
@@ -215,6 +569,35 @@ impl<R: Read + Seek, V: Visitor> Parser<R, V> {
 
         // TODO: do not allow tick to be greater then total ticks
 
+        let full_packet = self
+            .index
+            .as_ref()
+            .and_then(|index| index.find_full_packet(target_tick).copied());
+
+        if let Some((&snapshot_tick, _)) = self.snapshots.range(..=target_tick).next_back() {
+            let is_forward = self.ctx.tick >= 0 && target_tick >= self.ctx.tick;
+            // NOTE: restoring a snapshot is cheaper then re-running signon and
+            // handling a full packet, thus prefer snapshot unless full packet
+            // is closer to the target tick.
+            if (!is_forward || snapshot_tick > self.ctx.tick)
+                && full_packet.is_none_or(|fp| fp.tick <= snapshot_tick)
+            {
+                self.restore_snapshot(snapshot_tick)?;
+                return self.run(|_notnotself, cmd_header| {
+                    if cmd_header.tick > target_tick {
+                        Ok(ControlFlow::Break)
+                    } else {
+                        Ok(ControlFlow::HandleCmd)
+                    }
+                });
+            }
+        }
+
+        if let Some(index) = self.index.as_ref() {
+            let signon_end_offset = index.signon_end_offset();
+            return self.run_to_tick_indexed(target_tick, signon_end_offset, full_packet);
+        }
+
         // TODO: do not clear if seeking forward and there's no full packet on
         // the way to the wanted tick / if target tick is closer then full
         // packet interval
@@ -261,7 +644,9 @@ impl<R: Read + Seek, V: Visitor> Parser<R, V> {
                     // packet's packet
                     cmd.packet = None;
                 }
-                notnotself.handle_cmd_full_packet(cmd)?;
+                notnotself
+                    .handle_cmd_full_packet(cmd)
+                    .map_err(|err| notnotself.wrap_err(cmd_header, err))?;
 
                 did_handle_last_full_packet = !has_full_packet_ahead;
 
@@ -280,45 +665,264 @@ impl<R: Read + Seek, V: Visitor> Parser<R, V> {
         })
     }
 
+    // step_back brings state to the previous tick. it is meant to be used
+    // together with snapshots (see [`Self::set_snapshot_interval`]), otherwise
+    // each step is as expensive as a regular [`Self::run_to_tick`].
+    pub fn step_back(&mut self) -> Result<()> {
+        self.run_to_tick(self.ctx.tick - 1)
+    }
+
+    // set_snapshot_interval enables (or disables if interval is None)
+    // snapshots of context that are taken every `interval` ticks during runs.
+    // snapshots are kept in memory and are used by [`Self::run_to_tick`] to
+    // seek backwards.
+    //
+    // NOTE: each snapshot contains a copy of all entities and string tables,
+    // smaller intervals make seeks faster, but consume more memory.
+    pub fn set_snapshot_interval(&mut self, interval: Option<NonZeroU32>) {
+        // NOTE: intervals that are greater then i32::MAX are not different
+        // from i32::MAX, nothing lasts this long.
+        self.snapshot_interval =
+            interval.map(|interval| i32::try_from(interval.get()).unwrap_or(i32::MAX));
+        if interval.is_none() {
+            self.snapshots.clear();
+        }
+    }
+
+    fn maybe_take_snapshot(&mut self) -> Result<()> {
+        let Some(interval) = self.snapshot_interval else {
+            return Ok(());
+        };
+
+        // NOTE: ticks before 0 are "initialization"; there's nothing to
+        // snapshot.
+        let tick = self.ctx.tick;
+        if tick < 0 {
+            return Ok(());
+        }
+
+        // NOTE: when seeking back and running forward again same ticks will be
+        // visited multiple times; there's no need to take snapshots of them
+        // again.
+        let prev = self.snapshots.range(..=tick).next_back();
+        let next = self.snapshots.range(tick..).next();
+        if prev.is_some_and(|(&prev_tick, _)| tick - prev_tick < interval)
+            || next.is_some_and(|(&next_tick, _)| next_tick - tick < interval)
+        {
+            return Ok(());
+        }
+
+        let offset = self.demo_file.stream_position()?;
+        self.snapshots.insert(
+            tick,
+            Snapshot {
+                offset,
+                string_tables: self.ctx.string_tables.snapshot(),
+                entities: self.ctx.entities.clone(),
+            },
+        );
+
+        Ok(())
+    }
+
+    fn restore_snapshot(&mut self, tick: i32) -> Result<()> {
+        let Some(snapshot) = self.snapshots.get(&tick) else {
+            return Ok(());
+        };
+
+        self.demo_file.seek(SeekFrom::Start(snapshot.offset))?;
+
+        self.ctx.string_tables.restore(&snapshot.string_tables);
+        self.ctx.entities.clone_from(&snapshot.entities);
+
+        // NOTE: instance baseline references string table's user data which
+        // was just replaced, it must be re-built.
+        self.ctx.instance_baseline.clear();
+        if let (Some(string_table), Some(entity_classes)) = (
+            self.ctx
+                .string_tables
+                .find_table(INSTANCE_BASELINE_TABLE_NAME),
+            self.ctx.entity_classes.as_ref(),
+        ) {
+            self.ctx
+                .instance_baseline
+                .update(string_table, entity_classes.classes)?;
+        }
+
+        self.ctx.prev_tick = tick;
+        self.ctx.tick = tick;
+
+        Ok(())
+    }
+
+    fn run_to_tick_indexed(
+        &mut self,
+        target_tick: i32,
+        signon_end_offset: u64,
+        full_packet: Option<FullPacketEntry>,
+    ) -> Result<()> {
+        // NOTE: full packets are ignored during regular runs, which means that
+        // running forward from the current position is always correct. but if
+        // there's a full packet on the way to the target tick it is cheaper to
+        // jump to it then to handle all the deltas in between.
+        let is_forward = self.ctx.tick >= 0 && target_tick >= self.ctx.tick;
+        let has_full_packet_ahead = full_packet.is_some_and(|fp| fp.tick > self.ctx.tick);
+
+        if !is_forward || has_full_packet_ahead {
+            self.reset()?;
+
+            // init string tables, flattened serializers and entity classes
+            self.run(|notnotself, cmd_header| {
+                let cmd_offset = notnotself
+                    .demo_file
+                    .stream_position()?
+                    .saturating_sub(cmd_header.bytes_read as u64);
+                if cmd_offset >= signon_end_offset {
+                    Ok(ControlFlow::Break)
+                } else {
+                    Ok(ControlFlow::HandleCmd)
+                }
+            })?;
+
+            if let Some(full_packet) = full_packet {
+                self.demo_file.seek(SeekFrom::Start(full_packet.offset))?;
+
+                let cmd_header = self.demo_file.read_cmd_header()?;
+                debug_assert!(
+                    cmd_header.command == EDemoCommands::DemFullPacket,
+                    "expected full packet"
+                );
+                self.ctx.prev_tick = self.ctx.tick;
+                self.ctx.tick = cmd_header.tick;
+
+                let cmd_data = self.demo_file.read_cmd(&cmd_header)?;
+                self.visitor.on_cmd(&self.ctx, &cmd_header, cmd_data)?;
+
+                let cmd = CDemoFullPacket::decode(cmd_data)?;
+                self.handle_cmd_full_packet(cmd)
+                    .map_err(|err| self.wrap_err(&cmd_header, err))?;
+
+                if self.ctx.prev_tick != self.ctx.tick {
+                    self.visitor.on_tick_end(&self.ctx)?;
+                }
+            }
+        }
+
+        self.run(|_notnotself, cmd_header| {
+            if cmd_header.tick > target_tick {
+                Ok(ControlFlow::Break)
+            } else {
+                Ok(ControlFlow::HandleCmd)
+            }
+        })
+    }
+
+    // wrap_err attaches location of the cmd to the error.
+    fn wrap_err(&mut self, cmd_header: &CmdHeader, err: Error) -> Error {
+        // NOTE: by the time cmd is being handled it's been fully read, thus
+        // its header is located bytes_read + size bytes back. that is not true
+        // if cmd could not be read (e.g. file is truncated).
+        let offset = match err {
+            Error::DemoFile(_) => None,
+            _ => self.demo_file.stream_position().ok().map(|pos| {
+                pos.saturating_sub(cmd_header.bytes_read as u64 + cmd_header.size as u64)
+            }),
+        };
+        self.wrap_err_at(cmd_header, err, offset)
+    }
+
+    // ----
+
+    // NOTE: it wouldn't be very nice to expose DemoFile that is owned by Parser
+    // because it'll violate encapsulation; parser will lack control over the
+    // DemoFile's internal state which may lead to to unintended consequences.
+
+    // build_index performs a pass over the demo file to find all full packets;
+    // once index is built [`Self::run_to_tick`] will use it to jump directly to
+    // the closest full packet.
+    pub fn build_index(&mut self) -> Result<&DemoIndex> {
+        let index = DemoIndex::build(&mut self.demo_file)?;
+        Ok(self.index.insert(index))
+    }
+
+    // set_index allows to use an index that was built before (e.g. read from
+    // cache). the index is validated against the demo file.
+    pub fn set_index(&mut self, index: DemoIndex) -> Result<()> {
+        index.validate(&mut self.demo_file)?;
+        self.index = Some(index);
+        Ok(())
+    }
+
+    #[inline]
+    pub fn index(&self) -> Option<&DemoIndex> {
+        self.index.as_ref()
+    }
+
+    #[inline]
+    pub fn file_info(&mut self) -> Result<&CDemoFileInfo> {
+        self.demo_file.file_info().map_err(Error::from)
+    }
+
+    // game returns game detected from DemFileHeader cmd, if it was not
+    // handled yet falls back to file info.
+    pub fn game(&mut self) -> Result<Option<Game>> {
+        if self.ctx.game.is_some() {
+            return Ok(self.ctx.game);
+        }
+        Ok(Game::from_file_info(self.file_info()?))
+    }
+
+    #[inline]
+    pub fn ticks_per_second(&mut self) -> Result<f32> {
+        self.demo_file.ticks_per_second().map_err(Error::from)
+    }
+
+    #[inline]
+    pub fn ticks_per_frame(&mut self) -> Result<f32> {
+        self.demo_file.ticks_per_frame().map_err(Error::from)
+    }
+
+    #[inline]
+    pub fn total_ticks(&mut self) -> Result<i32> {
+        self.demo_file.total_ticks().map_err(Error::from)
+    }
+}
+
+impl<R: DemoRead, V: Visitor> Parser<R, V> {
     // important initialization messages:
     // 1. DemSignonPacket (SvcCreateStringTable)
     // 2. DemSendTables (flattened serializers; never update)
     // 3. DemClassInfo (never update)
     fn handle_cmd(&mut self, cmd_header: &CmdHeader) -> Result<()> {
+        self.packet_type = None;
         let data = self.demo_file.read_cmd(cmd_header)?;
         self.visitor.on_cmd(&self.ctx, cmd_header, data)?;
+        let cmd = DemoCmd::decode(&self.ctx, cmd_header.command, data)?;
+        self.handle_demo_cmd(cmd)
+    }
 
-        match cmd_header.command {
-            EDemoCommands::DemPacket | EDemoCommands::DemSignonPacket => {
-                let cmd = CDemoPacket::decode(data)?;
-                self.handle_cmd_packet(cmd)?;
+    fn handle_demo_cmd(&mut self, cmd: DemoCmd) -> Result<()> {
+        match cmd {
+            DemoCmd::FileHeader(game) => {
+                self.ctx.game = game;
             }
 
-            EDemoCommands::DemSendTables => {
-                // NOTE: this check exists because seeking exists, there's no
-                // need to re-parse flattened serializers
-                if self.ctx.serializers.is_some() {
-                    return Ok(());
-                }
-
-                let cmd = CDemoSendTables::decode(data)?;
-                self.ctx.serializers = Some(FlattenedSerializerContainer::parse(
-                    cmd,
-                    FlattenedSerializerContext {
-                        tick_interval: self.ctx.tick_interval,
-                    },
-                )?);
+            DemoCmd::Packet(cmd) => {
+                self.handle_cmd_packet(cmd)?;
             }
 
-            EDemoCommands::DemClassInfo => {
-                // NOTE: this check exists because seeking exists, there's no
-                // need to re-parse entity classes
-                if self.ctx.entity_classes.is_some() {
-                    return Ok(());
-                }
+            DemoCmd::SendTables(cmd) => {
+                let ctx = FlattenedSerializerContext {
+                    tick_interval: self.ctx.tick_interval,
+                };
+                self.ctx.serializers = Some(match self.serializer_cache.as_ref() {
+                    Some(serializer_cache) => serializer_cache.get_or_parse(cmd, ctx)?,
+                    None => FlattenedSerializerContainer::parse(cmd, ctx)?,
+                });
+            }
 
-                let cmd = CDemoClassInfo::decode(data)?;
-                self.ctx.entity_classes = Some(EntityClasses::parse(cmd));
+            DemoCmd::ClassInfo(cmd) => {
+                let entity_classes = self.ctx.entity_classes.insert(EntityClasses::parse(cmd));
 
                 // NOTE: DemClassInfo message becomes available after
                 // SvcCreateStringTable(which has instancebaselines). to know
@@ -330,18 +934,13 @@ impl<R: Read + Seek, V: Visitor> Parser<R, V> {
                     .string_tables
                     .find_table(INSTANCE_BASELINE_TABLE_NAME)
                 {
-                    // SAFETY: entity_classes value was assigned above ^.
-                    let entity_classes =
-                        unsafe { self.ctx.entity_classes.as_ref().unwrap_unchecked() };
                     self.ctx
                         .instance_baseline
                         .update(string_table, entity_classes.classes)?;
                 }
             }
 
-            _ => {
-                // ignore
-            }
+            DemoCmd::Ignored => {}
         }
 
         Ok(())
@@ -354,11 +953,18 @@ impl<R: Read + Seek, V: Visitor> Parser<R, V> {
             let command = br.read_ubitvar()?;
             let size = br.read_uvarint32()? as usize;
 
-            let buf = &mut self.buf[..size];
+            let buf = self
+                .buf
+                .get_mut(..size)
+                .ok_or(Error::OversizedPacketMessage(size))?;
             br.read_bytes(buf)?;
             let buf: &_ = buf;
 
+            self.packet_type = Some(command);
             self.visitor.on_packet(&self.ctx, command, buf)?;
+            if let Some(event_queue) = self.event_queue.as_mut() {
+                event_queue.push_packet(command, buf);
+            }
 
             match command {
                 c if c == SvcMessages::SvcCreateStringTable as u32 => {
@@ -386,6 +992,24 @@ impl<R: Read + Seek, V: Visitor> Parser<R, V> {
                     }
                 }
 
+                c if c == EBaseGameEvents::GeSource1LegacyGameEventList as u32 => {
+                    let msg = CMsgSource1LegacyGameEventList::decode(buf)?;
+                    self.ctx.game_event_list = Some(GameEventList::parse(msg));
+                }
+
+                c if c == EBaseGameEvents::GeSource1LegacyGameEvent as u32 => {
+                    // NOTE: game event list is sent during signon; events that
+                    // arrive before it (if that's even possible) can't be
+                    // decoded.
+                    if let Some(game_event_list) = self.ctx.game_event_list.as_ref() {
+                        let msg = CMsgSource1LegacyGameEvent::decode(buf)?;
+                        match game_event_list.decode_event(msg) {
+                            Ok(event) => self.visitor.on_game_event(&self.ctx, &event)?,
+                            Err(err) => self.visitor.on_game_event_error(&self.ctx, &err)?,
+                        }
+                    }
+                }
+
                 _ => {
                     // ignore
                 }
@@ -429,16 +1053,25 @@ impl<R: Read + Seek, V: Visitor> Parser<R, V> {
         debug_assert!(msg.table_id.is_some(), "invalid table id");
         let table_id = msg.table_id() as usize;
 
-        debug_assert!(
-            self.ctx.string_tables.has_table(table_id),
-            "tryting to update non-existent table"
-        );
-        let string_table = unsafe {
-            self.ctx
-                .string_tables
-                .get_table_mut(table_id)
-                .unwrap_unchecked()
+        #[cfg(not(feature = "safe"))]
+        let string_table = {
+            debug_assert!(
+                self.ctx.string_tables.has_table(table_id),
+                "tryting to update non-existent table"
+            );
+            unsafe {
+                self.ctx
+                    .string_tables
+                    .get_table_mut(table_id)
+                    .unwrap_unchecked()
+            }
         };
+        #[cfg(feature = "safe")]
+        let string_table = self
+            .ctx
+            .string_tables
+            .get_table_mut(table_id)
+            .ok_or(crate::stringtables::Error::UnknownStringTable(table_id))?;
         string_table.parse_update(
             &mut BitReader::new(msg.string_data()),
             msg.num_changed_entries(),
@@ -460,11 +1093,25 @@ impl<R: Read + Seek, V: Visitor> Parser<R, V> {
     fn handle_svc_packet_entities(&mut self, msg: CsvcMsgPacketEntities) -> Result<()> {
         use entities::*;
 
+        let wrap_err = |index: i32, source: entities::Error| crate::Error::Entity { index, source };
+
         // SAFETY: safety here can only be guaranteed by the fact that entity
         // classes and flattened serializers become available before packet
         // entities.
-        let entity_classes = unsafe { self.ctx.entity_classes.as_ref().unwrap_unchecked() };
-        let serializers = unsafe { self.ctx.serializers.as_ref().unwrap_unchecked() };
+        #[cfg(not(feature = "safe"))]
+        let (entity_classes, serializers) = unsafe {
+            (
+                self.ctx.entity_classes.as_ref().unwrap_unchecked(),
+                self.ctx.serializers.as_ref().unwrap_unchecked(),
+            )
+        };
+        #[cfg(feature = "safe")]
+        let (entity_classes, serializers) = self
+            .ctx
+            .entity_classes
+            .as_ref()
+            .zip(self.ctx.serializers.as_ref())
+            .ok_or(crate::Error::MissingSignon)?;
         let instance_baseline = &self.ctx.instance_baseline;
 
         let entity_data = msg.entity_data();
@@ -472,9 +1119,13 @@ impl<R: Read + Seek, V: Visitor> Parser<R, V> {
 
         let mut entity_index: i32 = -1;
         for _ in (0..msg.updated_entries()).rev() {
-            entity_index += br.read_ubitvar()? as i32 + 1;
+            entity_index +=
+                br.read_ubitvar()
+                    .map_err(|err| wrap_err(entity_index, err.into()))? as i32
+                    + 1;
 
-            let update_flags = parse_delta_header(&mut br)?;
+            let update_flags =
+                parse_delta_header(&mut br).map_err(|err| wrap_err(entity_index, err))?;
             let update_type = determine_update_type(update_flags);
 
             match update_type {
@@ -490,39 +1141,78 @@ impl<R: Read + Seek, V: Visitor> Parser<R, V> {
                     // think of any issues that may arrise because of my raw
                     // pointer approach.
                     let entity = unsafe {
-                        let entity = self.ctx.entities.handle_create(
-                            entity_index,
-                            &mut br,
-                            entity_classes,
-                            instance_baseline,
-                            serializers,
-                        )?;
+                        let entity = self
+                            .ctx
+                            .entities
+                            .handle_create(
+                                entity_index,
+                                &mut br,
+                                entity_classes,
+                                instance_baseline,
+                                serializers,
+                            )
+                            .map_err(|err| wrap_err(entity_index, err))?;
                         &*(entity as *const Entity)
                     };
-                    self.visitor
-                        .on_entity(&self.ctx, update_flags, update_type, entity)?;
+                    self.visitor.on_entity(
+                        &self.ctx,
+                        update_flags,
+                        update_type,
+                        entity,
+                        self.ctx.entities.changes(),
+                    )?;
+                    if let Some(event_queue) = self.event_queue.as_mut() {
+                        event_queue.push_entity(entity_index, update_flags, update_type);
+                    }
                 }
                 UpdateType::LeavePVS => {
                     if (update_flags & FHDR_DELETE) != 0 {
+                        #[cfg(not(feature = "safe"))]
                         let entity =
                             unsafe { self.ctx.entities.handle_delete_unchecked(entity_index) };
-                        self.visitor
-                            .on_entity(&self.ctx, update_flags, update_type, &entity)?;
+                        #[cfg(feature = "safe")]
+                        let entity = self
+                            .ctx
+                            .entities
+                            .handle_delete(entity_index)
+                            .map_err(|err| wrap_err(entity_index, err))?;
+                        self.visitor.on_entity(
+                            &self.ctx,
+                            update_flags,
+                            update_type,
+                            &entity,
+                            self.ctx.entities.changes(),
+                        )?;
+                        if let Some(event_queue) = self.event_queue.as_mut() {
+                            event_queue.push_entity(entity_index, update_flags, update_type);
+                        }
                     }
                 }
                 UpdateType::DeltaEnt => {
                     // SAFETY: see comment above for .handle_create call in
                     // EnterPVS arm; same stuff.
                     let entity = unsafe {
+                        #[cfg(not(feature = "safe"))]
                         let entity = self
                             .ctx
                             .entities
-                            .handle_update_unchecked(entity_index, &mut br)?;
+                            .handle_update_unchecked(entity_index, &mut br);
+                        #[cfg(feature = "safe")]
+                        let entity = self.ctx.entities.handle_update(entity_index, &mut br);
+                        let entity = entity.map_err(|err| wrap_err(entity_index, err))?;
                         &*(entity as *const Entity)
                     };
 
-                    self.visitor
-                        .on_entity(&self.ctx, update_flags, update_type, entity)?;
+                    self.visitor.on_entity(
+                        &self.ctx,
+                        update_flags,
+                        update_type,
+                        entity,
+                        self.ctx.entities.changes(),
+                    )?;
+                    if let Some(event_queue) = self.event_queue.as_mut() {
+                        event_queue.push_entity(entity_index, update_flags, update_type);
+                    }
                 }
             }
         }
@@ -530,16 +1220,33 @@ impl<R: Read + Seek, V: Visitor> Parser<R, V> {
         Ok(())
     }
 
+    fn wrap_err_at(&self, cmd_header: &CmdHeader, err: Error, offset: Option<u64>) -> Error {
+        if matches!(err, Error::Context { .. }) {
+            return err;
+        }
+
+        Error::Context {
+            context: Box::new(ErrorContext {
+                offset,
+                tick: cmd_header.tick,
+                cmd: cmd_header.command,
+                packet_type: self.packet_type,
+            }),
+            source: Box::new(err),
+        }
+    }
+
     fn handle_cmd_string_tables(&mut self, cmd: CDemoStringTables) -> Result<()> {
         self.ctx.string_tables.do_full_update(cmd);
 
-        // SAFETY: entity_classes value is expected to be already assigned
-        let entity_classes = unsafe { self.ctx.entity_classes.as_ref().unwrap_unchecked() };
-        if let Some(string_table) = self
-            .ctx
-            .string_tables
-            .find_table(INSTANCE_BASELINE_TABLE_NAME)
-        {
+        // NOTE: if entity classes are not available yet instance baseline
+        // will be updated when they arrive (see DemoCmd::ClassInfo).
+        if let (Some(string_table), Some(entity_classes)) = (
+            self.ctx
+                .string_tables
+                .find_table(INSTANCE_BASELINE_TABLE_NAME),
+            self.ctx.entity_classes.as_ref(),
+        ) {
             self.ctx
                 .instance_baseline
                 .update(string_table, entity_classes.classes)?;
@@ -549,6 +1256,8 @@ impl<R: Read + Seek, V: Visitor> Parser<R, V> {
     }
 
     fn handle_cmd_full_packet(&mut self, cmd: CDemoFullPacket) -> Result<()> {
+        // NOTE: full packets are sometimes handled outside of handle_cmd.
+        self.packet_type = None;
         if let Some(string_table) = cmd.string_table {
             self.handle_cmd_string_tables(string_table)?;
         }
@@ -560,11 +1269,12 @@ impl<R: Read + Seek, V: Visitor> Parser<R, V> {
         Ok(())
     }
 
-    // ----
-
-    // NOTE: it wouldn't be very nice to expose DemoFile that is owned by Parser
-    // because it'll violate encapsulation; parser will lack control over the
-    // DemoFile's internal state which may lead to to unintended consequences.
+    // set_serializer_cache makes parser take flattened serializers from the
+    // cache (or put them there) instead of parsing them for each replay.
+    #[inline]
+    pub fn set_serializer_cache(&mut self, serializer_cache: SerializerCache) {
+        self.serializer_cache = Some(serializer_cache);
+    }
 
     #[inline]
     pub fn demo_header(&self) -> &DemoHeader {
@@ -574,55 +1284,677 @@ impl<R: Read + Seek, V: Visitor> Parser<R, V> {
         unsafe { self.demo_file.demo_header_unchecked() }
     }
 
+    // NOTE: following methods are public-facing api; do not use them internally
+
     #[inline]
-    pub fn file_info(&mut self) -> Result<&CDemoFileInfo> {
-        self.demo_file.file_info().map_err(Error::from)
+    pub fn tick(&self) -> i32 {
+        self.ctx.tick()
     }
 
     #[inline]
-    pub fn ticks_per_second(&mut self) -> Result<f32> {
-        self.demo_file.ticks_per_second().map_err(Error::from)
+    pub fn string_tables(&self) -> Option<&StringTableContainer> {
+        self.ctx.string_tables()
     }
 
     #[inline]
-    pub fn ticks_per_frame(&mut self) -> Result<f32> {
-        self.demo_file.ticks_per_frame().map_err(Error::from)
+    pub fn serializers(&self) -> Option<&FlattenedSerializerContainer> {
+        self.ctx.serializers()
     }
 
     #[inline]
-    pub fn total_ticks(&mut self) -> Result<i32> {
-        self.demo_file.total_ticks().map_err(Error::from)
+    pub fn entities(&self) -> Option<&EntityContainer> {
+        self.ctx.entities()
     }
 
-    // NOTE: following methods are public-facing api; do not use them internally
-
     #[inline]
-    pub fn tick(&self) -> i32 {
-        self.ctx.tick()
+    pub fn game_event_list(&self) -> Option<&GameEventList> {
+        self.ctx.game_event_list()
     }
 
     #[inline]
-    pub fn string_tables(&self) -> Option<&StringTableContainer> {
-        self.ctx.string_tables()
+    pub fn visitor(&self) -> &V {
+        &self.visitor
     }
 
     #[inline]
-    pub fn serializers(&self) -> Option<&FlattenedSerializerContainer> {
-        self.ctx.serializers()
+    pub fn visitor_mut(&mut self) -> &mut V {
+        &mut self.visitor
     }
 
     #[inline]
-    pub fn entities(&self) -> Option<&EntityContainer> {
-        self.ctx.entities()
+    pub fn into_visitor(self) -> V {
+        self.visitor
+    }
+}
+
+// Commands is a lending iterator over cmds (see [`Parser::commands`]); it can't
+// implement Iterator because items borrow from it.
+//
+// NOTE: cmd data is not copied; it is borrowed from demo file's buffer (or
+// from the slice for SliceReader) that handling of the cmd does not touch.
+pub struct Commands<'p, R: DemoRead, V: Visitor> {
+    parser: &'p mut Parser<R, V>,
+}
+
+impl<'p, R: DemoSeek, V: Visitor> Commands<'p, R, V> {
+    // next handles the next cmd and yields it together with context that
+    // reflects it; visitor is called as usual.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Option<Result<(&Context, CmdHeader, &[u8])>> {
+        let cmd_header = match self.parser.next_cmd_header() {
+            Ok(Some(cmd_header)) => cmd_header,
+            Ok(None) => return None,
+            Err(err) => return Some(Err(err)),
+        };
+        if let Err(err) = self.parser.step(&cmd_header) {
+            return Some(Err(err));
+        }
+        match self.parser.demo_file.last_cmd(&cmd_header) {
+            Ok(data) => Some(Ok((&self.parser.ctx, cmd_header, data))),
+            Err(err) => Some(Err(Error::from(err))),
+        }
+    }
+}
+
+// Event is an item of [`Events`].
+#[derive(Debug)]
+pub enum Event<'a> {
+    // Entity is an entity update; entity is none if it was deleted.
+    //
+    // NOTE: field changes are available only to [`Visitor::on_entity`].
+    Entity {
+        index: i32,
+        update_flags: usize,
+        update_type: UpdateType,
+        entity: Option<&'a Entity>,
+    },
+    // Packet is a message of a packet (see [`Visitor::on_packet`]).
+    Packet {
+        packet_type: u32,
+        data: &'a [u8],
+    },
+    // TickEnd indicates that all cmds of tick were handled; it comes after the
+    // last cmd of the tick, context still reflects the tick.
+    //
+    // NOTE: tick -1 is signon, it never ends.
+    TickEnd {
+        tick: i32,
+    },
+}
+
+enum QueuedEvent {
+    Entity {
+        index: i32,
+        update_flags: usize,
+        update_type: UpdateType,
+    },
+    Packet {
+        packet_type: u32,
+        range: Range<usize>,
+    },
+    TickEnd {
+        tick: i32,
+    },
+}
+
+// EventQueue holds events of a single cmd; packet data of all events is stored
+// in one buffer.
+#[derive(Default)]
+struct EventQueue {
+    events: VecDeque<QueuedEvent>,
+    packet_data: Vec<u8>,
+}
+
+impl EventQueue {
+    fn push_entity(&mut self, index: i32, update_flags: usize, update_type: UpdateType) {
+        self.events.push_back(QueuedEvent::Entity {
+            index,
+            update_flags,
+            update_type,
+        });
+    }
+
+    fn push_packet(&mut self, packet_type: u32, data: &[u8]) {
+        let start = self.packet_data.len();
+        self.packet_data.extend_from_slice(data);
+        self.events.push_back(QueuedEvent::Packet {
+            packet_type,
+            range: start..self.packet_data.len(),
+        });
+    }
+}
+
+// Events is a lending iterator over events (see [`Parser::events`]). cmds are
+// handled one at a time, events are yielded once the whole cmd was handled,
+// thus context reflects the cmd (not the single event).
+pub struct Events<'p, R: DemoRead, V: Visitor> {
+    parser: &'p mut Parser<R, V>,
+    // tick_end is the last tick whose end was yielded.
+    tick_end: Option<i32>,
+}
+
+impl<'p, R: DemoSeek, V: Visitor> Events<'p, R, V> {
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Option<Result<(&Context, Event<'_>)>> {
+        let queued = loop {
+            let event_queue = self.parser.event_queue.get_or_insert_with(Default::default);
+            if let Some(queued) = event_queue.events.pop_front() {
+                break queued;
+            }
+            event_queue.packet_data.clear();
+
+            // NOTE: tick ends when the next cmd belongs to another tick (or
+            // when there are no more cmds); the cmd is kept around until the
+            // tick end is yielded.
+            let next_cmd_header = match self.parser.next_cmd_header() {
+                Ok(next_cmd_header) => next_cmd_header,
+                Err(err) => return Some(Err(err)),
+            };
+            let tick = self.parser.ctx.tick;
+            let is_tick_end = tick != -1
+                && self.tick_end != Some(tick)
+                && next_cmd_header
+                    .as_ref()
+                    .is_none_or(|cmd_header| cmd_header.tick != tick);
+
+            let Some(cmd_header) = next_cmd_header else {
+                if is_tick_end {
+                    self.tick_end = Some(tick);
+                    break QueuedEvent::TickEnd { tick };
+                }
+                return None;
+            };
+            if is_tick_end {
+                self.parser.pending_cmd_header = Some(cmd_header);
+                self.tick_end = Some(tick);
+                break QueuedEvent::TickEnd { tick };
+            }
+
+            if let Err(err) = self.parser.step(&cmd_header) {
+                return Some(Err(err));
+            }
+        };
+
+        let ctx = &self.parser.ctx;
+        let event = match queued {
+            QueuedEvent::Entity {
+                index,
+                update_flags,
+                update_type,
+            } => Event::Entity {
+                index,
+                update_flags,
+                update_type,
+                entity: ctx.entities.get(&index),
+            },
+            QueuedEvent::Packet { packet_type, range } => Event::Packet {
+                packet_type,
+                data: self
+                    .parser
+                    .event_queue
+                    .as_ref()
+                    .and_then(|event_queue| event_queue.packet_data.get(range))
+                    .unwrap_or_default(),
+            },
+            QueuedEvent::TickEnd { tick } => Event::TickEnd { tick },
+        };
+        Some(Ok((ctx, event)))
+    }
+}
+
+impl<'p, R: DemoRead, V: Visitor> Drop for Events<'p, R, V> {
+    fn drop(&mut self) {
+        // NOTE: parser must not collect events when nobody consumes them.
+        self.parser.event_queue = None;
     }
 }
 
 pub struct NopVisitor;
 impl Visitor for NopVisitor {}
 
-impl<R: Read + Seek> Parser<R, NopVisitor> {
+impl<R: DemoRead> Parser<R, NopVisitor> {
     #[inline]
     pub fn from_reader(rdr: R) -> Result<Self> {
         Self::from_reader_with_visitor(rdr, NopVisitor)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        bitbuf::BitWriter,
+        demofile::{self, make_demo, push_cmd, push_cmd_header},
+    };
+    use std::{
+        cell::RefCell,
+        io::{self, Read, Seek},
+        rc::Rc,
+    };
+
+    // GrowingFile imitates a file that is being appended to while it's read.
+    #[derive(Default, Clone)]
+    struct GrowingFile {
+        data: Rc<RefCell<Vec<u8>>>,
+        pos: u64,
+    }
+
+    impl GrowingFile {
+        fn append(&self, bytes: &[u8]) {
+            self.data.borrow_mut().extend_from_slice(bytes);
+        }
+    }
+
+    impl Read for GrowingFile {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let data = self.data.borrow();
+            let start = (self.pos as usize).min(data.len());
+            let n = buf.len().min(data.len() - start);
+            buf[..n].copy_from_slice(&data[start..start + n]);
+            self.pos += n as u64;
+            Ok(n)
+        }
+    }
+
+    impl Seek for GrowingFile {
+        fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+            let len = self.data.borrow().len() as i64;
+            self.pos = match pos {
+                SeekFrom::Start(offset) => offset as i64,
+                SeekFrom::Current(offset) => self.pos as i64 + offset,
+                SeekFrom::End(offset) => len + offset,
+            } as u64;
+            Ok(self.pos)
+        }
+    }
+
+    #[test]
+    fn test_run_tail() -> Result<()> {
+        let file = GrowingFile::default();
+        file.append(&make_demo(|_| {}));
+
+        let mut parser = Parser::from_reader(file.clone())?;
+        assert_eq!(parser.run_tail_to_end()?, TailStatus::WouldBlock);
+
+        // incomplete cmd header
+        let sync_tick = EDemoCommands::DemSyncTick as u8;
+        file.append(&[sync_tick]);
+        assert_eq!(parser.run_tail_to_end()?, TailStatus::WouldBlock);
+        assert_eq!(parser.tick(), -1);
+
+        file.append(&[0, 0]);
+        assert_eq!(parser.run_tail_to_end()?, TailStatus::WouldBlock);
+        assert_eq!(parser.tick(), 0);
+
+        // incomplete cmd body
+        file.append(&[sync_tick, 1, 2, 0]);
+        assert_eq!(parser.run_tail_to_end()?, TailStatus::WouldBlock);
+        assert_eq!(parser.tick(), 0);
+
+        file.append(&[0]);
+        assert_eq!(parser.run_tail_to_end()?, TailStatus::WouldBlock);
+        assert_eq!(parser.tick(), 1);
+
+        file.append(&[EDemoCommands::DemStop as u8, 1, 0]);
+        assert_eq!(parser.run_tail_to_end()?, TailStatus::Stop);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_run_tail_corrupt_cmd_header() -> Result<()> {
+        let file = GrowingFile::default();
+        file.append(&make_demo(|_| {}));
+
+        let mut parser = Parser::from_reader(file.clone())?;
+
+        // NOTE: 63 is not a valid cmd; the header is incomplete though, more
+        // bytes may arrive.
+        file.append(&[63]);
+        assert_eq!(parser.run_tail_to_end()?, TailStatus::WouldBlock);
+
+        file.append(&[0, 0]);
+        let err = parser.run_tail_to_end().err();
+        assert!(matches!(
+            err.as_ref().map(Error::inner),
+            Some(Error::DemoFile(demofile::Error::UnknownCmd(63)))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_run_stream() -> Result<()> {
+        let sync_tick = EDemoCommands::DemSyncTick as u8;
+        let stop = EDemoCommands::DemStop as u8;
+        let data = make_demo(|data| {
+            data.extend_from_slice(&[sync_tick, 0, 2, 0, 0]);
+            data.extend_from_slice(&[sync_tick, 1, 0]);
+            data.extend_from_slice(&[stop, 1, 0]);
+        });
+
+        // NOTE: &[u8] does not implement Seek.
+        let mut parser = Parser::from_reader(data.as_slice())?;
+
+        let mut cmds = Vec::new();
+        parser.run_stream(|_notnotself, cmd_header| {
+            if cmd_header.command == EDemoCommands::DemStop {
+                return Ok(ControlFlow::Break);
+            }
+            cmds.push(cmd_header.tick);
+            Ok(ControlFlow::SkipCmd)
+        })?;
+        assert_eq!(cmds, [0, 1]);
+        assert_eq!(parser.tick(), 1);
+
+        let mut cmds = Vec::new();
+        parser.run_stream(|_notnotself, cmd_header| {
+            cmds.push(cmd_header.command);
+            Ok(ControlFlow::HandleCmd)
+        })?;
+        assert_eq!(cmds, [EDemoCommands::DemStop]);
+
+        Ok(())
+    }
+
+    fn make_packet(messages: &[(u32, Vec<u8>)]) -> Vec<u8> {
+        let mut bw = BitWriter::default();
+        for (command, msg) in messages {
+            bw.write_ubitvar(*command);
+            bw.write_uvarint32(msg.len() as u32);
+            bw.write_bytes(msg);
+        }
+        CDemoPacket {
+            data: Some(bw.finish_unpadded()),
+        }
+        .encode_to_vec()
+    }
+
+    #[test]
+    fn test_oversized_cmd() -> Result<()> {
+        let data = make_demo(|data| {
+            push_cmd_header(data, EDemoCommands::DemPacket, 0, DEMO_BUFFER_SIZE + 1);
+        });
+        let mut parser = Parser::from_reader(io::Cursor::new(&data))?;
+        let err = parser.run_to_end().err();
+        assert!(matches!(
+            err.as_ref().map(Error::inner),
+            Some(Error::DemoFile(demofile::Error::OversizedCmd(size))) if *size as usize == DEMO_BUFFER_SIZE + 1
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_truncated_cmd() -> Result<()> {
+        let data = make_demo(|data| {
+            push_cmd_header(data, EDemoCommands::DemPacket, 0, 10);
+            data.extend_from_slice(&[0; 3]);
+        });
+        let mut parser = Parser::from_reader(io::Cursor::new(&data))?;
+        assert!(parser.run_to_end().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_oversized_packet_message() -> Result<()> {
+        let mut bw = BitWriter::default();
+        bw.write_ubitvar(SvcMessages::SvcServerInfo as u32);
+        bw.write_uvarint32(DEMO_BUFFER_SIZE as u32 + 1);
+        bw.write_bytes(&[0; 4]);
+        let packet = CDemoPacket {
+            data: Some(bw.finish_unpadded()),
+        }
+        .encode_to_vec();
+        let data = make_demo(|data| push_cmd(data, EDemoCommands::DemPacket, 0, &packet));
+
+        let mut parser = Parser::from_reader(io::Cursor::new(&data))?;
+        let err = parser.run_to_end().err();
+        assert!(matches!(
+            err.as_ref().map(Error::inner),
+            Some(Error::OversizedPacketMessage(_))
+        ));
+        Ok(())
+    }
+
+    #[cfg(feature = "safe")]
+    #[test]
+    fn test_unknown_string_table() -> Result<()> {
+        let update_string_table = CsvcMsgUpdateStringTable {
+            table_id: Some(3),
+            num_changed_entries: Some(0),
+            string_data: Some(Vec::new()),
+        };
+        let packet = make_packet(&[(
+            SvcMessages::SvcUpdateStringTable as u32,
+            update_string_table.encode_to_vec(),
+        )]);
+        let data = make_demo(|data| push_cmd(data, EDemoCommands::DemPacket, 0, &packet));
+
+        let mut parser = Parser::from_reader(io::Cursor::new(&data))?;
+        let err = parser.run_to_end().err();
+        assert!(matches!(
+            err.as_ref().map(Error::inner),
+            Some(Error::StringTables(
+                crate::stringtables::Error::UnknownStringTable(3)
+            ))
+        ));
+        Ok(())
+    }
+
+    // make_string_table_demo makes a demo with a single string table; each tick
+    // entry 0's user data is set to the tick and an entry with the tick's
+    // string is added.
+    fn make_string_table_demo(ticks: u8) -> Vec<u8> {
+        fn write_user_data(bw: &mut BitWriter, user_data: u8) {
+            bw.write_bool(true);
+            // NOTE: MAX_USERDATA_BITS
+            bw.write_ubitlong(1, 17);
+            bw.write_bytes(&[user_data]);
+        }
+
+        let mut bw = BitWriter::default();
+        bw.write_bool(true);
+        bw.write_bool(true);
+        bw.write_bool(false);
+        bw.write_bytes(b"entry 0\0");
+        write_user_data(&mut bw, 0);
+        let create_string_table = CsvcMsgCreateStringTable {
+            name: Some("test".to_string()),
+            num_entries: Some(1),
+            string_data: Some(bw.finish()),
+            ..Default::default()
+        };
+
+        make_demo(|data| {
+            push_cmd(
+                data,
+                EDemoCommands::DemSignonPacket,
+                0,
+                &make_packet(&[(
+                    SvcMessages::SvcCreateStringTable as u32,
+                    create_string_table.encode_to_vec(),
+                )]),
+            );
+            push_cmd(data, EDemoCommands::DemSyncTick, 0, &[]);
+            push_cmd(
+                data,
+                EDemoCommands::DemFullPacket,
+                0,
+                &CDemoFullPacket::default().encode_to_vec(),
+            );
+            for tick in 1..=ticks {
+                let mut bw = BitWriter::default();
+                // entry 0
+                bw.write_bool(true);
+                bw.write_bool(false);
+                write_user_data(&mut bw, tick);
+                // entry tick; index is encoded as a delta from the previous one.
+                bw.write_bool(false);
+                bw.write_uvarint32(tick as u32 - 1);
+                bw.write_bool(true);
+                bw.write_bool(false);
+                bw.write_bytes(format!("entry {tick}\0").as_bytes());
+                bw.write_bool(false);
+                let update_string_table = CsvcMsgUpdateStringTable {
+                    table_id: Some(0),
+                    num_changed_entries: Some(2),
+                    string_data: Some(bw.finish()),
+                };
+
+                push_cmd(
+                    data,
+                    EDemoCommands::DemPacket,
+                    tick,
+                    &make_packet(&[(
+                        SvcMessages::SvcUpdateStringTable as u32,
+                        update_string_table.encode_to_vec(),
+                    )]),
+                );
+            }
+            push_cmd(data, EDemoCommands::DemStop, ticks, &[]);
+        })
+    }
+
+    type StringTableState = Vec<(i32, Option<Vec<u8>>, Option<Vec<u8>>)>;
+
+    fn string_table_state<R: DemoRead, V: Visitor>(parser: &Parser<R, V>) -> StringTableState {
+        let mut state: StringTableState = parser
+            .string_tables()
+            .and_then(|string_tables| string_tables.find_table("test"))
+            .into_iter()
+            .flat_map(|string_table| string_table.items())
+            .map(|(index, item)| {
+                let user_data = item.user_data.as_ref().map(|v| v.to_vec());
+                (*index, item.string.clone(), user_data)
+            })
+            .collect();
+        state.sort_by_key(|(index, ..)| *index);
+        state
+    }
+
+    #[test]
+    fn test_step_back() -> Result<()> {
+        let data = make_string_table_demo(6);
+
+        let mut parser = Parser::from_reader(io::Cursor::new(&data))?;
+        parser.set_snapshot_interval(NonZeroU32::new(2));
+        parser.run_to_tick(6)?;
+        assert_eq!(parser.tick(), 6);
+        assert_eq!(string_table_state(&parser).len(), 7);
+        assert_eq!(
+            parser.snapshots.keys().copied().collect::<Vec<_>>(),
+            [0, 2, 4, 6]
+        );
+
+        for tick in (0..6).rev() {
+            parser.step_back()?;
+            assert_eq!(parser.tick(), tick);
+
+            let mut fresh_parser = Parser::from_reader(io::Cursor::new(&data))?;
+            fresh_parser.run_to_tick(tick)?;
+            assert_eq!(fresh_parser.tick(), tick);
+
+            let state = string_table_state(&parser);
+            assert_eq!(state, string_table_state(&fresh_parser));
+            assert_eq!(state.len(), tick as usize + 1);
+            assert_eq!(state[0].2, Some(vec![tick as u8]));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_commands_and_events() -> Result<()> {
+        let sync_tick = EDemoCommands::DemSyncTick as u8;
+        let packet = EDemoCommands::DemPacket as u8;
+
+        // packet with a single message (type 4, 2 bytes); parser does not
+        // handle messages of type 4.
+        let msg: u32 = 4 | (2 << 6) | (0xaa << 14) | (0xbb << 22);
+        let packet_data = CDemoPacket {
+            data: Some(msg.to_le_bytes().to_vec()),
+        }
+        .encode_to_vec();
+
+        let compressed_packet_data = snap::raw::Encoder::new().compress_vec(&packet_data)?;
+
+        let data = make_demo(|data| {
+            data.extend_from_slice(&[sync_tick, 0, 0]);
+            data.extend_from_slice(&[packet, 1, packet_data.len() as u8]);
+            data.extend_from_slice(&packet_data);
+            // NOTE: tick 2 consists of 2 cmds, the first one is compressed.
+            data.extend_from_slice(&[
+                packet | EDemoCommands::DemIsCompressed as u8,
+                2,
+                compressed_packet_data.len() as u8,
+            ]);
+            data.extend_from_slice(&compressed_packet_data);
+            data.extend_from_slice(&[packet, 2, packet_data.len() as u8]);
+            data.extend_from_slice(&packet_data);
+        });
+
+        let expected_cmds = [
+            (EDemoCommands::DemSyncTick, 0, vec![]),
+            (EDemoCommands::DemPacket, 1, packet_data.clone()),
+            (EDemoCommands::DemPacket, 2, packet_data.clone()),
+            (EDemoCommands::DemPacket, 2, packet_data.clone()),
+        ];
+
+        // NOTE: cmds of slice readers are borrowed from the slice.
+        let mut parser = Parser::from_reader(demofile::SliceReader::new(&data))?;
+        let mut commands = parser.commands();
+        let mut cmds = Vec::new();
+        while let Some(result) = commands.next() {
+            let (ctx, cmd_header, cmd_data) = result?;
+            assert_eq!(ctx.tick(), cmd_header.tick);
+            if !cmd_header.is_compressed && !cmd_data.is_empty() {
+                assert!(data.as_ptr_range().contains(&cmd_data.as_ptr()));
+            }
+            cmds.push((cmd_header.command, cmd_header.tick, cmd_data.to_vec()));
+        }
+        assert_eq!(cmds, expected_cmds);
+
+        let mut parser = Parser::from_reader(io::Cursor::new(&data))?;
+        parser.set_snapshot_interval(NonZeroU32::new(1));
+        let mut commands = parser.commands();
+        let mut cmds = Vec::new();
+        while let Some(result) = commands.next() {
+            let (_ctx, cmd_header, cmd_data) = result?;
+            cmds.push((cmd_header.command, cmd_header.tick, cmd_data.to_vec()));
+        }
+        assert_eq!(cmds, expected_cmds);
+        // NOTE: commands take snapshots just like runs do.
+        assert_eq!(
+            parser.snapshots.keys().copied().collect::<Vec<_>>(),
+            [0, 1, 2]
+        );
+
+        let mut parser = Parser::from_reader(io::Cursor::new(&data))?;
+        let mut events = parser.events();
+        let mut got = Vec::new();
+        while let Some(result) = events.next() {
+            let (ctx, event) = result?;
+            got.push(match event {
+                Event::Entity { index, .. } => format!("entity {index}"),
+                Event::Packet { packet_type, data } => format!("packet {packet_type} {data:?}"),
+                Event::TickEnd { tick } => {
+                    assert_eq!(ctx.tick(), tick);
+                    format!("tick end {tick}")
+                }
+            });
+        }
+        assert_eq!(
+            got,
+            [
+                "tick end 0",
+                "packet 4 [170, 187]",
+                "tick end 1",
+                "packet 4 [170, 187]",
+                "packet 4 [170, 187]",
+                "tick end 2",
+            ]
+        );
+
+        Ok(())
+    }
+}
@@ -1,10 +1,11 @@
 use crate::{
     bitbuf::{self, BitReader},
     protos::{c_demo_string_tables, CDemoStringTables},
+    Shared,
 };
 use hashbrown::HashMap;
 use nohash::NoHashHasher;
-use std::{cell::UnsafeCell, hash::BuildHasherDefault, mem::MaybeUninit, rc::Rc};
+use std::{hash::BuildHasherDefault, mem::MaybeUninit};
 
 // NOTE: some info about string tables is available at
 // https://developer.valvesoftware.com/wiki/Networking_Events_%26_Messages#String_Tables
@@ -20,6 +21,8 @@ pub enum Error {
     // mod
     #[error("tried to create string table '{0}' twice")]
     DuplicateStringTable(String),
+    #[error("string table {0} does not exist")]
+    UnknownStringTable(usize),
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
@@ -49,10 +52,62 @@ impl StringHistoryEntry {
 const MAX_USERDATA_BITS: usize = 17;
 const MAX_USERDATA_SIZE: usize = 1 << MAX_USERDATA_BITS;
 
-#[derive(Debug)]
+// NOTE: user data is shared with instance baseline and snapshots; it is
+// updated in place unless it's shared (see parse_update).
+#[derive(Debug, Clone)]
 pub struct StringTableItem {
     pub string: Option<Vec<u8>>,
-    pub user_data: Option<Rc<UnsafeCell<Vec<u8>>>>,
+    pub user_data: Option<Shared<Vec<u8>>>,
+}
+
+// NOTE: strings are serialized lossily (they are supposed to be valid utf8
+// anyway), user data is serialized as bytes.
+#[cfg(feature = "serde")]
+impl serde::Serialize for StringTableItem {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        struct Bytes<'a>(&'a [u8]);
+
+        impl<'a> serde::Serialize for Bytes<'a> {
+            fn serialize<S: serde::Serializer>(
+                &self,
+                serializer: S,
+            ) -> std::result::Result<S::Ok, S::Error> {
+                serializer.serialize_bytes(self.0)
+            }
+        }
+
+        let mut state = serializer.serialize_struct("StringTableItem", 2)?;
+        state.serialize_field(
+            "string",
+            &self.string.as_ref().map(|v| String::from_utf8_lossy(v)),
+        )?;
+        state.serialize_field(
+            "user_data",
+            &self.user_data.as_ref().map(|v| Bytes(v.as_slice())),
+        )?;
+        state.end()
+    }
+}
+
+type ItemMap = HashMap<i32, StringTableItem, BuildHasherDefault<NoHashHasher<i32>>>;
+
+// StringTableSnapshot is a copy of string table's state; it does not include
+// scratch buffers (history, string_buf, etc.).
+#[derive(Debug, Clone)]
+pub(crate) struct StringTableSnapshot {
+    name: Box<str>,
+    user_data_fixed_size: bool,
+    user_data_size: i32,
+    user_data_size_bits: i32,
+    flags: i32,
+    using_varint_bitcounts: bool,
+
+    items: ItemMap,
 }
 
 #[derive(Debug)]
@@ -64,7 +119,7 @@ pub struct StringTable {
     flags: i32,
     using_varint_bitcounts: bool,
 
-    items: HashMap<i32, StringTableItem, BuildHasherDefault<NoHashHasher<i32>>>,
+    items: ItemMap,
 
     history: Vec<StringHistoryEntry>,
     string_buf: Vec<u8>,
@@ -232,14 +287,17 @@ impl StringTable {
             self.items
                 .entry(entry_index)
                 .and_modify(|entry| {
-                    if let Some(dst_container) = entry.user_data.as_ref() {
+                    if let Some(dst_container) = entry.user_data.as_mut() {
                         if let Some(src) = user_data {
-                            let dst = unsafe { dst_container.get().as_mut().unwrap_unchecked() };
+                            // NOTE: make_mut clones user data if it's shared
+                            // (e.g. with instance baseline which is updated
+                            // right after string table updates).
+                            let dst = Shared::make_mut(dst_container);
                             dst.resize(src.len(), 0);
                             dst.clone_from_slice(src);
                         }
                     } else {
-                        entry.user_data = user_data.map(|v| Rc::new(UnsafeCell::new(v.to_vec())));
+                        entry.user_data = user_data.map(|v| Shared::new(v.to_vec()));
                     }
                 })
                 .or_insert_with(|| StringTableItem {
@@ -248,7 +306,7 @@ impl StringTable {
                         dst.extend_from_slice(src);
                         dst
                     }),
-                    user_data: user_data.map(|v| Rc::new(UnsafeCell::new(v.to_vec()))),
+                    user_data: user_data.map(|v| Shared::new(v.to_vec())),
                 });
         }
 
@@ -271,17 +329,12 @@ impl StringTable {
             self.items
                 .entry(i as i32)
                 .and_modify(|existing| {
-                    existing.user_data = incoming
-                        .data
-                        .as_ref()
-                        .map(|data| Rc::new(UnsafeCell::new(data.clone())))
+                    existing.user_data =
+                        incoming.data.as_ref().map(|data| Shared::new(data.clone()))
                 })
                 .or_insert_with(|| StringTableItem {
                     string: incoming.str.as_ref().map(|v| v.as_bytes().to_vec()),
-                    user_data: incoming
-                        .data
-                        .as_ref()
-                        .map(|data| Rc::new(UnsafeCell::new(data.clone()))),
+                    user_data: incoming.data.as_ref().map(|data| Shared::new(data.clone())),
                 });
         }
     }
@@ -291,6 +344,18 @@ impl StringTable {
     // void EnableRollback();
     // void RestoreTick(int tick);
 
+    pub(crate) fn snapshot(&self) -> StringTableSnapshot {
+        StringTableSnapshot {
+            name: self.name.clone(),
+            user_data_fixed_size: self.user_data_fixed_size,
+            user_data_size: self.user_data_size,
+            user_data_size_bits: self.user_data_size_bits,
+            flags: self.flags,
+            using_varint_bitcounts: self.using_varint_bitcounts,
+            items: self.items.clone(),
+        }
+    }
+
     #[inline]
     pub fn name(&self) -> &str {
         self.name.as_ref()
@@ -302,6 +367,32 @@ impl StringTable {
     }
 }
 
+#[cfg(feature = "serde")]
+impl serde::Serialize for StringTable {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        struct Items<'a>(&'a ItemMap);
+
+        impl<'a> serde::Serialize for Items<'a> {
+            fn serialize<S: serde::Serializer>(
+                &self,
+                serializer: S,
+            ) -> std::result::Result<S::Ok, S::Error> {
+                serializer.collect_map(self.0.iter())
+            }
+        }
+
+        let mut state = serializer.serialize_struct("StringTable", 2)?;
+        state.serialize_field("name", &self.name)?;
+        state.serialize_field("items", &Items(&self.items))?;
+        state.end()
+    }
+}
+
 // NOTE: this is modelled after CNetworkStringTableContainer
 #[derive(Default)]
 pub struct StringTableContainer {
@@ -405,9 +496,46 @@ impl StringTableContainer {
     // void EnableRollback( bool bState );
     // void RestoreTick( int tick );
 
+    pub(crate) fn snapshot(&self) -> Vec<StringTableSnapshot> {
+        self.tables.iter().map(StringTable::snapshot).collect()
+    }
+
+    // restore brings tables into the state they were in when snapshot was
+    // taken; tables that did not exist at that point are removed.
+    pub(crate) fn restore(&mut self, snapshot: &[StringTableSnapshot]) {
+        let mut tables = Vec::with_capacity(snapshot.len());
+        for sts in snapshot {
+            let mut table = match self.tables.iter().position(|table| table.name == sts.name) {
+                // NOTE: reuse existing table to not re-allocate its buffers
+                Some(i) => self.tables.swap_remove(i),
+                None => StringTable::new(
+                    &sts.name,
+                    sts.user_data_fixed_size,
+                    sts.user_data_size,
+                    sts.user_data_size_bits,
+                    sts.flags,
+                    sts.using_varint_bitcounts,
+                ),
+            };
+            table.items.clone_from(&sts.items);
+            tables.push(table);
+        }
+        self.tables = tables;
+    }
+
     // TODO: rename to iter?
     #[inline]
     pub fn tables(&self) -> impl Iterator<Item = &StringTable> {
         self.tables.iter()
     }
 }
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for StringTableContainer {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.tables.iter())
+    }
+}
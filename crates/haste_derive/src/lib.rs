@@ -0,0 +1,197 @@
+//! derive macros for haste; they are re-exported by haste when derive feature
+//! is enabled.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{
+    parse_macro_input, spanned::Spanned, Attribute, Data, DeriveInput, Error, Fields,
+    GenericArgument, LitStr, PathArguments, PathSegment, Type,
+};
+
+/// derives `TryFrom<&haste::entities::Entity>`.
+///
+/// ```ignore
+/// #[derive(EntityView)]
+/// #[haste(class = "CDOTATeam")]
+/// struct Team {
+///     #[haste(field = "m_iTeamNum")]
+///     team_num: u8,
+///     #[haste(field = "m_iHeroKills")]
+///     hero_kills: i32,
+///     // NOTE: fields without attribute are looked up by their names.
+///     m_iScore: Option<i32>,
+/// }
+/// ```
+///
+/// field keys and serializer name hash are computed at compile time. fields
+/// are converted with `haste::entities::Entity::get`, which means that field
+/// types must implement `TryFrom<&haste::fieldvalue::FieldValue>`; `Option`
+/// fields are set to `None` when entity does not have them. if struct has a
+/// lifetime parameter (e.g. to borrow `&'a str`) it is used as the lifetime of
+/// the entity reference.
+///
+/// fixed arrays (e.g. `int32[4]`) can be bound to `[T; N]` fields and dynamic
+/// arrays (e.g. `CNetworkUtlVectorBase< int32 >`) to `Vec<T>` fields; they are
+/// collected with `haste::entities::Entity::get_array` and
+/// `haste::entities::Entity::get_vec`.
+///
+/// also generates an associated `SERIALIZER_NAME_HASH` constant that can be
+/// used to cheaply check whether an entity is of the class before converting
+/// it.
+#[proc_macro_derive(EntityView, attributes(haste))]
+pub fn derive_entity_view(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_entity_view(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn expand_entity_view(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let class = parse_haste_attr(&input.attrs, "class")?.ok_or_else(|| {
+        Error::new(
+            input.ident.span(),
+            "missing #[haste(class = \"...\")] attribute",
+        )
+    })?;
+
+    let Data::Struct(data) = &input.data else {
+        return Err(Error::new(
+            input.ident.span(),
+            "EntityView can only be derived for structs",
+        ));
+    };
+    let Fields::Named(fields) = &data.fields else {
+        return Err(Error::new(
+            input.ident.span(),
+            "EntityView can only be derived for structs with named fields",
+        ));
+    };
+
+    let mut inits = Vec::with_capacity(fields.named.len());
+    for field in fields.named.iter() {
+        let ident = field
+            .ident
+            .as_ref()
+            .ok_or_else(|| Error::new(field.span(), "expected named field"))?;
+
+        let (path, span) = match parse_haste_attr(&field.attrs, "field")? {
+            Some(lit) => (lit.value(), lit.span()),
+            None => (ident.to_string(), ident.span()),
+        };
+        let parts: Vec<&str> = path.split('.').collect();
+        // NOTE: make_field_key hashes all parts as strings, but indices of
+        // dynamic arrays are hashed as numbers.
+        if parts
+            .iter()
+            .any(|part| part.is_empty() || part.parse::<usize>().is_ok())
+        {
+            return Err(Error::new(
+                span,
+                "array items can't be addressed, use Entity::get_by_path instead",
+            ));
+        }
+
+        let (ty, optional) = match option_inner(&field.ty) {
+            Some(inner) => (inner, true),
+            None => (&field.ty, false),
+        };
+        let get = match ty {
+            Type::Array(_) => quote! { get_array },
+            _ if is_vec(ty) => quote! { get_vec },
+            _ => quote! { get },
+        };
+        let value = if optional {
+            quote! {
+                match entity.#get(&KEY) {
+                    Ok(value) => Some(value),
+                    Err(::haste::entities::Error::MissingField(_)) => None,
+                    Err(err) => return Err(err),
+                }
+            }
+        } else {
+            quote! { entity.#get(&KEY)? }
+        };
+        inits.push(quote! {
+            #ident: {
+                const KEY: u64 = ::haste::entities::make_field_key(&[#(#parts),*]);
+                #value
+            }
+        });
+    }
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let entity_ref = match input.generics.lifetimes().next() {
+        Some(lifetime) => {
+            let lifetime = &lifetime.lifetime;
+            quote! { &#lifetime ::haste::entities::Entity }
+        }
+        None => quote! { &::haste::entities::Entity },
+    };
+
+    Ok(quote! {
+        impl #impl_generics #name #ty_generics #where_clause {
+            pub const SERIALIZER_NAME_HASH: u64 = ::haste::fxhash::hash_bytes(#class.as_bytes());
+        }
+
+        impl #impl_generics ::core::convert::TryFrom<#entity_ref> for #name #ty_generics #where_clause {
+            type Error = ::haste::entities::Error;
+
+            fn try_from(entity: #entity_ref) -> ::core::result::Result<Self, Self::Error> {
+                let serializer_name_hash = entity.get_serializer().serializer_name.hash;
+                if serializer_name_hash != Self::SERIALIZER_NAME_HASH {
+                    return Err(::haste::entities::Error::UnexpectedSerializer {
+                        want: #class,
+                        got: serializer_name_hash,
+                    });
+                }
+
+                Ok(Self {
+                    #(#inits,)*
+                })
+            }
+        }
+    })
+}
+
+// parse_haste_attr looks for #[haste(name = "value")] attribute.
+fn parse_haste_attr(attrs: &[Attribute], name: &str) -> syn::Result<Option<LitStr>> {
+    let mut ret = None;
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("haste")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident(name) {
+                ret = Some(meta.value()?.parse::<LitStr>()?);
+                Ok(())
+            } else {
+                Err(meta.error(format!("unsupported haste attribute, expected `{name}`")))
+            }
+        })?;
+    }
+    Ok(ret)
+}
+
+// last_segment returns last segment of a path type, e.g. `Option<T>` of
+// `std::option::Option<T>`.
+fn last_segment(ty: &Type) -> Option<&PathSegment> {
+    match ty {
+        Type::Path(type_path) => type_path.path.segments.last(),
+        _ => None,
+    }
+}
+
+// option_inner returns `T` of `Option<T>`.
+fn option_inner(ty: &Type) -> Option<&Type> {
+    let segment = last_segment(ty).filter(|segment| segment.ident == "Option")?;
+    let PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return None;
+    };
+    match args.args.first() {
+        Some(GenericArgument::Type(ty)) => Some(ty),
+        _ => None,
+    }
+}
+
+fn is_vec(ty: &Type) -> bool {
+    last_segment(ty).is_some_and(|segment| segment.ident == "Vec")
+}
@@ -0,0 +1,9 @@
+use haste::EntityView;
+
+#[derive(EntityView)]
+struct Team {
+    #[haste(field = "m_iTeamNum")]
+    team_num: u8,
+}
+
+fn main() {}
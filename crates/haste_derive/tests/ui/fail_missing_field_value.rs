@@ -0,0 +1,10 @@
+use haste::EntityView;
+
+#[derive(EntityView)]
+#[haste(class = "CDOTATeam")]
+struct Team {
+    #[haste(field)]
+    team_num: u8,
+}
+
+fn main() {}
@@ -0,0 +1,46 @@
+use haste::{entities::Entity, EntityView};
+
+#[derive(EntityView)]
+#[haste(class = "CDOTATeam")]
+struct Team {
+    #[haste(field = "m_iTeamNum")]
+    team_num: u8,
+    #[allow(non_snake_case)]
+    m_iScore: Option<i32>,
+}
+
+#[derive(EntityView)]
+#[haste(class = "CDOTAPlayerController")]
+struct Player<'a> {
+    #[haste(field = "m_iszPlayerName")]
+    name: &'a str,
+    #[haste(field = "m_pEntity.m_nameStringableIndex")]
+    name_index: i32,
+}
+
+#[derive(EntityView)]
+#[haste(class = "CDOTA_DataRadiant")]
+struct Data<'a> {
+    #[haste(field = "m_iReliableGold")]
+    reliable_gold: [i32; 24],
+    #[haste(field = "m_vecDataTeam")]
+    names: Vec<&'a str>,
+    #[haste(field = "m_iUnreliableGold")]
+    unreliable_gold: Option<[i32; 24]>,
+    #[haste(field = "m_vecValues")]
+    values: Option<Vec<u64>>,
+}
+
+fn convert(entity: &Entity) -> Result<(Team, Player<'_>), haste::entities::Error> {
+    Ok((Team::try_from(entity)?, Player::try_from(entity)?))
+}
+
+fn convert_data(entity: &Entity) -> Result<Data<'_>, haste::entities::Error> {
+    Data::try_from(entity)
+}
+
+fn main() {
+    let _ = convert;
+    let _ = convert_data;
+    let _ = Team::SERIALIZER_NAME_HASH;
+}
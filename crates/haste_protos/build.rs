@@ -14,6 +14,7 @@ fn main() -> std::io::Result<()> {
 
     let shared_protos = vec![
         "demo.proto",
+        "gameevents.proto",
         "netmessages.proto",
         "network_connection.proto",
         "networkbasetypes.proto",
@@ -24,13 +25,30 @@ fn main() -> std::io::Result<()> {
     let deadlock_protos = vec![
         "citadel_gcmessages_common.proto",
         "citadel_usermessages.proto",
-        "gameevents.proto",
         "gcsdk_gcmessages.proto",
         "steammessages.proto",
         "steammessages_steamlearn.steamworkssdk.proto",
         "steammessages_unified_base.steamworkssdk.proto",
     ];
 
+    // NOTE: cs2 protos are a trimmed, hand-written subset of
+    // https://github.com/SteamDatabase/GameTracking-CS2/tree/master/Protobufs
+    // (see headers of the files). they are meant to be replaced with verbatim
+    // upstream files, same as dota2 and deadlock ones:
+    //
+    //   $ cd crates/haste_protos/protos/cs2
+    //   $ for file in cs_gameevents.proto cstrike15_usermessages.proto; do
+    //       curl -LO "https://raw.githubusercontent.com/SteamDatabase/GameTracking-CS2/master/Protobufs/$file"
+    //     done
+    //
+    // upstream files import gc messages (cstrike15_gcmessages.proto,
+    // engine_gcmessages.proto, gcsdk_gcmessages.proto, steammessages.proto,
+    // ...); fetch everything that protoc reports as missing the same way and
+    // list it below (see deadlock protos), otherwise imported types end up
+    // outside of cs2 module.
+    #[cfg(feature = "cs2")]
+    let cs2_protos = vec!["cs_gameevents.proto", "cstrike15_usermessages.proto"];
+
     #[cfg(feature = "dota2")]
     let dota2_protos = vec![
         "dota_commonmessages.proto",
@@ -38,24 +56,96 @@ fn main() -> std::io::Result<()> {
         "dota_usermessages.proto",
     ];
 
-    let protos = vec![
-        shared_protos,
-        #[cfg(feature = "deadlock")]
-        deadlock_protos,
-        #[cfg(feature = "dota2")]
-        dota2_protos,
-    ]
-    .into_iter()
-    .flatten()
-    .collect::<Vec<&str>>();
-
-    let includes = [
-        "protos",
-        #[cfg(feature = "deadlock")]
-        "protos/deadlock",
-        #[cfg(feature = "dota2")]
-        "protos/dota2",
-    ];
+    prost_build::compile_protos(&shared_protos, &["protos"])?;
+
+    #[cfg(feature = "cs2")]
+    compile_game_protos("cs2", &cs2_protos)?;
+    #[cfg(feature = "deadlock")]
+    compile_game_protos("deadlock", &deadlock_protos)?;
+    #[cfg(feature = "dota2")]
+    compile_game_protos("dota2", &dota2_protos)?;
+
+    Ok(())
+}
+
+// compile_game_protos compiles protos of a game into a module of its own so
+// that games do not clash with each other (for example cs2 and deadlock both
+// have steammessages.proto).
+//
+// NOTE: valve's protos do not declare packages. to make prost emit
+// OUT_DIR/<game>/<game>.rs (that refers to shared types via super::) game's
+// files are moved into <game> package, and references to their types are
+// prefixed accordingly.
+#[allow(dead_code)]
+fn compile_game_protos(game: &str, protos: &[&str]) -> std::io::Result<()> {
+    let out_dir =
+        std::path::Path::new(&std::env::var("OUT_DIR").map_err(std::io::Error::other)?).join(game);
+    std::fs::create_dir_all(&out_dir)?;
+
+    let mut config = prost_build::Config::new();
+    config.out_dir(&out_dir);
+
+    // NOTE: game's include goes first to take precedence over shared protos.
+    let mut fds = config.load_fds(protos, &[format!("protos/{game}"), "protos".to_string()])?;
+
+    // NOTE: nested types are covered by their top level names.
+    let game_type_names: std::collections::HashSet<String> = fds
+        .file
+        .iter()
+        .filter(|file| protos.contains(&file.name()))
+        .flat_map(|file| {
+            let message_names = file.message_type.iter().map(|msg| msg.name());
+            let enum_names = file.enum_type.iter().map(|enm| enm.name());
+            message_names.chain(enum_names).map(String::from)
+        })
+        .collect();
+    let prefix_type_name = |type_name: &mut String| {
+        let top_level_name = type_name.trim_start_matches('.').split('.').next();
+        if top_level_name.is_some_and(|name| game_type_names.contains(name)) {
+            *type_name = format!(".{game}{type_name}");
+        }
+    };
+
+    for file in fds.file.iter_mut() {
+        if protos.contains(&file.name()) {
+            file.package = Some(game.to_string());
+        }
+        for msg in file.message_type.iter_mut() {
+            prefix_message_type_names(msg, &prefix_type_name);
+        }
+        for field in file.extension.iter_mut() {
+            if let Some(type_name) = field.type_name.as_mut() {
+                prefix_type_name(type_name);
+            }
+        }
+        for method in file
+            .service
+            .iter_mut()
+            .flat_map(|svc| svc.method.iter_mut())
+        {
+            if let Some(type_name) = method.input_type.as_mut() {
+                prefix_type_name(type_name);
+            }
+            if let Some(type_name) = method.output_type.as_mut() {
+                prefix_type_name(type_name);
+            }
+        }
+    }
+
+    config.compile_fds(fds)
+}
 
-    prost_build::compile_protos(&protos, &includes)
+#[allow(dead_code)]
+fn prefix_message_type_names(
+    msg: &mut prost_types::DescriptorProto,
+    prefix_type_name: &impl Fn(&mut String),
+) {
+    for field in msg.field.iter_mut().chain(msg.extension.iter_mut()) {
+        if let Some(type_name) = field.type_name.as_mut() {
+            prefix_type_name(type_name);
+        }
+    }
+    for msg in msg.nested_type.iter_mut() {
+        prefix_message_type_names(msg, prefix_type_name);
+    }
 }
@@ -1,4 +1,32 @@
 include!(concat!(env!("OUT_DIR"), "/_.rs"));
 
+// NOTE: game specific protos live in their own modules, see build.rs.
+
+#[cfg(feature = "cs2")]
+pub mod cs2 {
+    include!(concat!(env!("OUT_DIR"), "/cs2/cs2.rs"));
+}
+
+#[cfg(feature = "deadlock")]
+pub mod deadlock {
+    include!(concat!(env!("OUT_DIR"), "/deadlock/deadlock.rs"));
+}
+
+#[cfg(feature = "dota2")]
+pub mod dota2 {
+    include!(concat!(env!("OUT_DIR"), "/dota2/dota2.rs"));
+}
+
+// NOTE: game specific protos used to live at the root; keep them reachable by
+// their old paths (e.g. haste::protos::EDotaUserMessages). if more than one
+// game is enabled names that exist in several games are only reachable through
+// their modules.
+#[cfg(feature = "cs2")]
+pub use cs2::*;
+#[cfg(feature = "deadlock")]
+pub use deadlock::*;
+#[cfg(feature = "dota2")]
+pub use dota2::*;
+
 // re-export
 pub use prost;
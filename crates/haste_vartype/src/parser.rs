@@ -224,4 +224,47 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn cs2_var_types() {
+        const INPUTS: [&str; 4] = [
+            "CGlobalSymbol",
+            "CUtlStringToken",
+            "GameTick_t",
+            "CNetworkUtlVectorBase< GameTick_t >",
+        ];
+
+        let outputs: Vec<Result<Expr<'static>>> = INPUTS.iter().map(|input| parse(input)).collect();
+
+        let expected = expect![[r#"
+            [
+                Ok(
+                    Ident(
+                        "CGlobalSymbol",
+                    ),
+                ),
+                Ok(
+                    Ident(
+                        "CUtlStringToken",
+                    ),
+                ),
+                Ok(
+                    Ident(
+                        "GameTick_t",
+                    ),
+                ),
+                Ok(
+                    Template {
+                        expr: Ident(
+                            "CNetworkUtlVectorBase",
+                        ),
+                        arg: Ident(
+                            "GameTick_t",
+                        ),
+                    },
+                ),
+            ]
+        "#]];
+        expected.assert_debug_eq(&outputs);
+    }
 }
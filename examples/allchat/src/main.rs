@@ -8,8 +8,8 @@ struct MyVisitor;
 
 impl Visitor for MyVisitor {
     fn on_packet(&mut self, _ctx: &Context, packet_type: u32, data: &[u8]) -> parser::Result<()> {
-        if packet_type == protos::EDotaUserMessages::DotaUmChatMessage as u32 {
-            let msg = protos::CdotaUserMsgChatMessage::decode(data)?;
+        if packet_type == protos::dota2::EDotaUserMessages::DotaUmChatMessage as u32 {
+            let msg = protos::dota2::CdotaUserMsgChatMessage::decode(data)?;
             println!("{:?}", msg);
         }
         Ok(())
@@ -29,5 +29,6 @@ fn main() -> Result<()> {
     let file = File::open(filepath.unwrap())?;
     let buf_reader = BufReader::new(file);
     let mut parser = Parser::from_reader_with_visitor(buf_reader, MyVisitor)?;
-    parser.run_to_end()
+    parser.run_to_end()?;
+    Ok(())
 }
@@ -1,6 +1,5 @@
 use haste::{
     entities::{self, Entity},
-    fieldvalue::FieldValue,
     parser::{self, Context, Parser, Visitor},
     protos::{self, prost::Message},
     stringtables::StringTable,
@@ -11,11 +10,11 @@ struct MyVisitor;
 
 fn get_entity_name<'a>(entity: &'a Entity, entity_names: &'a StringTable) -> Option<&'a str> {
     let name_si_key = entities::make_field_key(&["m_pEntity", "m_nameStringableIndex"]);
-    let Some(FieldValue::I32(name_si)) = entity.get_value(&name_si_key) else {
+    let Ok(name_si) = entity.get::<i32>(&name_si_key) else {
         return None;
     };
 
-    let Some((_, name_st_item)) = entity_names.items().find(|(i, _)| i.eq(&name_si)) else {
+    let Some((_, name_st_item)) = entity_names.items().find(|(i, _)| **i == name_si) else {
         return None;
     };
 
@@ -28,8 +27,8 @@ fn get_entity_name<'a>(entity: &'a Entity, entity_names: &'a StringTable) -> Opt
 
 impl Visitor for MyVisitor {
     fn on_packet(&mut self, ctx: &Context, packet_type: u32, data: &[u8]) -> parser::Result<()> {
-        if packet_type == protos::CitadelUserMessageIds::KEUserMsgHeroKilled as u32 {
-            let msg = protos::CCitadelUserMsgHeroKilled::decode(data)?;
+        if packet_type == protos::deadlock::CitadelUserMessageIds::KEUserMsgHeroKilled as u32 {
+            let msg = protos::deadlock::CCitadelUserMsgHeroKilled::decode(data)?;
 
             let entities = ctx.entities().unwrap();
 
@@ -62,5 +61,6 @@ fn main() -> Result<()> {
     let file = File::open(filepath.unwrap())?;
     let buf_reader = BufReader::new(file);
     let mut parser = Parser::from_reader_with_visitor(buf_reader, MyVisitor)?;
-    parser.run_to_end()
+    parser.run_to_end()?;
+    Ok(())
 }
@@ -1,18 +1,16 @@
 use haste::{
-    entities::{make_field_key, Entity, UpdateType},
-    fieldvalue::FieldValue,
+    entities::{make_field_key, Entity, FieldChange, UpdateType},
     parser::{self, Context, Parser, Visitor},
 };
-use std::{collections::HashMap, fs::File, io::BufReader};
+use std::{fs::File, io::BufReader};
 
 // public/const.h
 const LIFE_ALIVE: u8 = 0; // alive
 const LIFE_DEAD: u8 = 2; // dead. lying still.
 
-#[derive(Default)]
-struct MyVisitor {
-    life_states: HashMap<i32, u8>,
-}
+const LIFE_STATE_KEY: u64 = make_field_key(&["m_lifeState"]);
+
+struct MyVisitor;
 
 impl Visitor for MyVisitor {
     fn on_entity(
@@ -21,20 +19,23 @@ impl Visitor for MyVisitor {
         _update_flags: usize,
         _update_type: UpdateType,
         entity: &Entity,
+        changes: &[FieldChange],
     ) -> parser::Result<()> {
-        let life_state_key = make_field_key(&["m_lifeState"]);
-        let Some(FieldValue::U8(next_life_state)) = entity.get_value(&life_state_key).cloned()
-        else {
+        let Some(change) = changes.iter().find(|change| change.key == LIFE_STATE_KEY) else {
             return Ok(());
         };
 
-        let prev_life_state = *self.life_states.get(&entity.index()).unwrap_or(&LIFE_DEAD);
+        // NOTE: for entities that enter pvs previous value is the one from
+        // instance baseline; if there's none the entity is considered dead.
+        let prev_life_state = match change.prev_value.as_ref() {
+            Some(prev_value) => u8::try_from(prev_value)?,
+            None => LIFE_DEAD,
+        };
+        let next_life_state = entity.get::<u8>(&LIFE_STATE_KEY)?;
         if next_life_state == prev_life_state {
             return Ok(());
         }
 
-        self.life_states.insert(entity.index(), next_life_state);
-
         match next_life_state {
             LIFE_ALIVE => eprintln!(
                 "{:>6}: {} at index {} has spawned",
@@ -67,6 +68,7 @@ fn main() -> Result<()> {
 
     let file = File::open(filepath.unwrap())?;
     let buf_reader = BufReader::new(file);
-    let mut parser = Parser::from_reader_with_visitor(buf_reader, MyVisitor::default())?;
-    parser.run_to_end()
+    let mut parser = Parser::from_reader_with_visitor(buf_reader, MyVisitor)?;
+    parser.run_to_end()?;
+    Ok(())
 }
@@ -1,11 +1,19 @@
 use haste::{
-    entities::{self, Entity, UpdateType},
-    fieldvalue::FieldValue,
-    fxhash,
+    entities::{Entity, FieldChange, UpdateType},
     parser::{self, Context, Parser, Visitor},
+    EntityView,
 };
 use std::{fs::File, io::BufReader};
 
+#[derive(EntityView)]
+#[haste(class = "CDOTATeam")]
+struct Team {
+    #[haste(field = "m_iTeamNum")]
+    team_num: u8,
+    #[haste(field = "m_iHeroKills")]
+    hero_kills: i32,
+}
+
 struct MyVisitor;
 
 impl Visitor for MyVisitor {
@@ -15,19 +23,15 @@ impl Visitor for MyVisitor {
         _update_flags: usize,
         _update_type: UpdateType,
         entity: &Entity,
+        _changes: &[FieldChange],
     ) -> parser::Result<()> {
-        if entity
-            .get_serializer()
-            .serializer_name
-            .hash
-            .eq(&fxhash::hash_bytes(b"CDOTATeam"))
-        {
-            let team_num_key = entities::make_field_key(&["m_iTeamNum"]);
-            let team_num = entity.get_value(&team_num_key);
-            if team_num.is_some_and(|team_num| matches!(team_num, FieldValue::U8(team_num) if *team_num == 2 || *team_num == 3)) {
-                let hero_kills_key = entities::make_field_key(&["m_iHeroKills"]);
-                let hero_kills = entity.get_value(&hero_kills_key);
-                println!("team_num: {:?}; hero_kills: {:?}", team_num, hero_kills);
+        if entity.get_serializer().serializer_name.hash == Team::SERIALIZER_NAME_HASH {
+            let team = Team::try_from(entity)?;
+            if team.team_num == 2 || team.team_num == 3 {
+                println!(
+                    "team_num: {:?}; hero_kills: {:?}",
+                    team.team_num, team.hero_kills
+                );
             }
         }
 
@@ -48,5 +52,6 @@ fn main() -> Result<()> {
     let file = File::open(filepath.unwrap())?;
     let buf_reader = BufReader::new(file);
     let mut parser = Parser::from_reader_with_visitor(buf_reader, MyVisitor)?;
-    parser.run_to_end()
+    parser.run_to_end()?;
+    Ok(())
 }
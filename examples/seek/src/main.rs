@@ -18,6 +18,14 @@ fn main() -> Result<()> {
     let buf_reader = BufReader::new(file);
     let mut parser = Parser::from_reader(buf_reader)?;
 
+    let start = Instant::now();
+    let index = parser.build_index()?;
+    println!(
+        "building index took {:?}, {} full packets",
+        start.elapsed(),
+        index.full_packets().len()
+    );
+
     let mut rng = rand::thread_rng();
     let rng_range = -1..parser.total_ticks()?;
 
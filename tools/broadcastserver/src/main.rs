@@ -0,0 +1,118 @@
+// broadcastserver cuts a demo file into broadcast fragments and serves them
+// the way a broadcast relay would; it is a local stand-in for testing
+// haste::broadcastclient (broadcast-client feature) against.
+//
+// fragment 0 is the start (signup) fragment, every full packet starts a new
+// fragment whose delta is made of cmds that follow the full packet.
+
+use haste::{
+    demofile::{DemoFile, SliceReader},
+    protos::EDemoCommands,
+};
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::TcpListener,
+};
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+struct Fragment {
+    tick: i32,
+    full: Vec<u8>,
+    delta: Vec<u8>,
+}
+
+fn cut_fragments(data: &[u8]) -> Result<(Vec<u8>, Vec<Fragment>)> {
+    let mut demo_file = DemoFile::from_reader(SliceReader::new(data));
+    demo_file.read_demo_header()?;
+
+    let mut start = Vec::new();
+    let mut fragments: Vec<Fragment> = Vec::new();
+    let mut did_handle_sync_tick = false;
+
+    loop {
+        let offset = demo_file.stream_position()? as usize;
+        let Some(cmd_header) = demo_file.try_read_cmd_header()? else {
+            break;
+        };
+        demo_file.skip_cmd(&cmd_header)?;
+        let cmd = &data[offset..demo_file.stream_position()? as usize];
+
+        match cmd_header.command {
+            EDemoCommands::DemStop => break,
+            _ if !did_handle_sync_tick => {
+                start.extend_from_slice(cmd);
+                did_handle_sync_tick = cmd_header.command == EDemoCommands::DemSyncTick;
+            }
+            EDemoCommands::DemFullPacket => fragments.push(Fragment {
+                tick: cmd_header.tick,
+                full: cmd.to_vec(),
+                delta: Vec::new(),
+            }),
+            // NOTE: cmds between signon and the first full packet are dropped,
+            // clients join at full packets.
+            _ => {
+                if let Some(fragment) = fragments.last_mut() {
+                    fragment.delta.extend_from_slice(cmd);
+                }
+            }
+        }
+    }
+
+    Ok((start, fragments))
+}
+
+fn route(path: &str, start: &[u8], fragments: &[Fragment]) -> Option<Vec<u8>> {
+    if path == "/sync" {
+        let tick = fragments.first().map(|fragment| fragment.tick)?;
+        let sync = format!(r#"{{"tick":{tick},"fragment":1,"signup_fragment":0}}"#);
+        return Some(sync.into_bytes());
+    }
+
+    let (fragment, fragment_type) = path.trim_start_matches('/').split_once('/')?;
+    let fragment: usize = fragment.parse().ok()?;
+    match (fragment, fragment_type) {
+        (0, "start") => Some(start.to_vec()),
+        (fragment, "full") if fragment > 0 => fragments
+            .get(fragment - 1)
+            .map(|fragment| fragment.full.clone()),
+        (fragment, "delta") if fragment > 0 => fragments
+            .get(fragment - 1)
+            .map(|fragment| fragment.delta.clone()),
+        _ => None,
+    }
+}
+
+fn main() -> Result<()> {
+    let args: Vec<String> = std::env::args().collect();
+    let filepath = args.get(1);
+    if filepath.is_none() {
+        eprintln!("usage: broadcastserver <filepath> [addr]");
+        std::process::exit(42);
+    }
+    let addr = args.get(2).map(String::as_str).unwrap_or("127.0.0.1:8080");
+
+    let data = std::fs::read(filepath.unwrap())?;
+    let (start, fragments) = cut_fragments(&data)?;
+    eprintln!("serving {} fragments at http://{addr}", fragments.len() + 1);
+
+    let listener = TcpListener::bind(addr)?;
+    for stream in listener.incoming() {
+        let mut stream = stream?;
+
+        let mut lines = BufReader::new(&stream).lines().map_while(|line| line.ok());
+        let request_line = lines.next().unwrap_or_default();
+        lines.take_while(|line| !line.is_empty()).for_each(drop);
+        let path = request_line.split_whitespace().nth(1).unwrap_or_default();
+
+        let response = match route(path, &start, &fragments) {
+            Some(body) => [b"HTTP/1.0 200 OK\r\n\r\n".to_vec(), body].concat(),
+            None => b"HTTP/1.0 404 Not Found\r\n\r\n".to_vec(),
+        };
+        if let Err(err) = stream.write_all(&response) {
+            eprintln!("could not respond to {path}: {err}");
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,382 @@
+use std::{
+    fmt::Write as _,
+    fs::File,
+    io::{BufReader, Read, Seek, Write},
+};
+
+use anyhow::{anyhow, Result};
+use haste::{
+    fieldmetadata::FieldSpecialDescriptor,
+    flattenedserializers::FlattenedSerializer,
+    parser::{ControlFlow, NopVisitor, Parser},
+    protos::EDemoCommands,
+};
+
+// NOTE: entitybindings emits a rust module with an EntityView struct per
+// flattened serializer (the module requires haste's derive feature). the output
+// is meant to be checked in and regenerated after game updates:
+//
+//   cargo run --release -p entitybindings -- <filepath> > src/bindings.rs
+
+fn parse_to_serializers<R: Read + Seek>(parser: &mut Parser<R, NopVisitor>) -> haste::Result<()> {
+    parser.reset()?;
+    parser.run(|notnotself, cmd_header| {
+        if notnotself.serializers().is_some() {
+            return Ok(ControlFlow::Break);
+        }
+        if cmd_header.command == EDemoCommands::DemSendTables {
+            return Ok(ControlFlow::HandleCmd);
+        }
+        Ok(ControlFlow::SkipCmd)
+    })
+}
+
+// ----
+
+const KEYWORDS: &[&str] = &[
+    "as", "async", "await", "box", "break", "const", "continue", "crate", "dyn", "else", "enum",
+    "extern", "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move",
+    "mut", "pub", "ref", "return", "static", "struct", "super", "trait", "true", "type", "unsafe",
+    "use", "where", "while", "yield",
+];
+
+fn make_ident(name: &str) -> String {
+    let mut ident: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    if ident.starts_with(|c: char| c.is_ascii_digit()) {
+        ident.insert(0, '_');
+    }
+    if KEYWORDS.contains(&ident.as_str()) {
+        ident.insert_str(0, "r#");
+    }
+    ident
+}
+
+// value_type maps FieldValue variant (see FieldValue::type_name) to rust type
+// that can be converted from it (see TryFrom impls in fieldvalue.rs).
+fn value_type(value_type_name: Option<&str>) -> Option<&'static str> {
+    match value_type_name? {
+        "I8" => Some("i8"),
+        "I16" => Some("i16"),
+        "I32" => Some("i32"),
+        "I64" => Some("i64"),
+        "U8" => Some("u8"),
+        "U16" => Some("u16"),
+        "U32" => Some("u32"),
+        "U64" => Some("u64"),
+        "Bool" => Some("bool"),
+        "F32" => Some("f32"),
+        "Vector" | "QAngle" => Some("[f32; 3]"),
+        "Vector2D" => Some("[f32; 2]"),
+        "Vector4D" => Some("[f32; 4]"),
+        // NOTE: strings are borrowed from entities.
+        "String" => Some("&'a str"),
+        _ => None,
+    }
+}
+
+struct Binding {
+    var_type: String,
+    path: String,
+    ty: Result<String, &'static str>,
+}
+
+// collect_bindings flattens fields of (nested) serializers into dotted paths
+// that EntityView derive macro can look up.
+//
+// NOTE: fixed arrays are bound to `[T; N]` and dynamic arrays to `Vec<T>`
+// (EntityView collects their items), but arrays of serializers can't be bound;
+// fields behind pointers are optional.
+fn collect_bindings(
+    out: &mut Vec<Binding>,
+    serializer: &FlattenedSerializer,
+    prefix: &str,
+    optional: bool,
+) {
+    for field in &serializer.fields {
+        let path = if prefix.is_empty() {
+            field.var_name.str.to_string()
+        } else {
+            format!("{prefix}.{}", field.var_name.str)
+        };
+        let var_type = field.var_type.str.to_string();
+
+        let ty = match field.metadata.special_descriptor.as_ref() {
+            Some(FieldSpecialDescriptor::FixedArray { length }) => {
+                let item = field
+                    .field_serializer
+                    .as_ref()
+                    .and_then(|field_serializer| field_serializer.fields.first());
+                match item {
+                    Some(item) if item.field_serializer.is_some() => {
+                        Err("arrays of serializers can't be bound, use Entity::get_by_path")
+                    }
+                    Some(item) => value_type(item.metadata.decoder.value_type_name())
+                        .map(|ty| format!("[{ty}; {length}]"))
+                        .ok_or("unknown value type"),
+                    None => Err("unknown item type"),
+                }
+            }
+            Some(FieldSpecialDescriptor::DynamicArray { decoder }) => {
+                value_type(decoder.value_type_name())
+                    .map(|ty| format!("Vec<{ty}>"))
+                    .ok_or("unknown value type")
+            }
+            Some(FieldSpecialDescriptor::DynamicSerializerArray) => {
+                Err("arrays of serializers can't be bound, use Entity::get_by_path")
+            }
+            _ if field.field_serializer_name.is_some() => match field.field_serializer.as_ref() {
+                Some(field_serializer) => {
+                    let is_pointer = matches!(
+                        field.metadata.special_descriptor,
+                        Some(FieldSpecialDescriptor::Pointer)
+                    );
+                    collect_bindings(out, field_serializer, &path, optional || is_pointer);
+                    continue;
+                }
+                None => Err("unknown serializer"),
+            },
+            _ => value_type(field.metadata.decoder.value_type_name())
+                .map(str::to_string)
+                .ok_or("unknown value type"),
+        };
+        let ty = ty.map(|ty| {
+            if optional {
+                format!("Option<{ty}>")
+            } else {
+                ty
+            }
+        });
+
+        out.push(Binding { var_type, path, ty });
+    }
+}
+
+fn write_serializer(out: &mut String, serializer: &FlattenedSerializer) -> std::fmt::Result {
+    let name = &serializer.serializer_name;
+    let ident = make_ident(&name.str);
+
+    let mut bindings = Vec::new();
+    collect_bindings(&mut bindings, serializer, "", false);
+    let generics = if bindings
+        .iter()
+        .any(|binding| binding.ty.as_ref().is_ok_and(|ty| ty.contains("'a")))
+    {
+        "<'a>"
+    } else {
+        ""
+    };
+
+    writeln!(out, "#[derive(Debug, Clone, EntityView)]")?;
+    writeln!(out, "#[haste(class = {:?})]", name.str)?;
+    writeln!(out, "pub struct {ident}{generics} {{")?;
+    for binding in &bindings {
+        match binding.ty.as_ref() {
+            Ok(ty) => {
+                writeln!(out, "    /// {}", binding.var_type)?;
+                writeln!(out, "    #[haste(field = {:?})]", binding.path)?;
+                // NOTE: dots are replaced with double underscores to not
+                // collide with var names that contain underscores.
+                writeln!(
+                    out,
+                    "    pub {}: {ty},",
+                    make_ident(&binding.path.replace('.', "__"))
+                )?;
+            }
+            Err(reason) => {
+                writeln!(
+                    out,
+                    "    // {} ({}): {reason}",
+                    binding.path, binding.var_type
+                )?;
+            }
+        }
+    }
+    writeln!(out, "}}")?;
+    writeln!(out)?;
+    writeln!(out, "impl{generics} {ident}{generics} {{")?;
+    writeln!(
+        out,
+        "    pub const SERIALIZER_NAME: &'static str = {:?};",
+        name.str
+    )?;
+    writeln!(out, "}}")?;
+    writeln!(out)
+}
+
+fn generate<'a>(
+    serializers: impl IntoIterator<Item = &'a FlattenedSerializer>,
+) -> std::result::Result<String, std::fmt::Error> {
+    let mut serializers: Vec<&FlattenedSerializer> = serializers.into_iter().collect();
+    serializers.sort_by(|a, b| a.serializer_name.str.cmp(&b.serializer_name.str));
+
+    let mut out = String::new();
+    writeln!(out, "// generated by entitybindings; do not edit.")?;
+    writeln!(out)?;
+    writeln!(
+        out,
+        "#![allow(non_camel_case_types, non_snake_case, dead_code)]"
+    )?;
+    writeln!(out)?;
+    writeln!(out, "use haste::EntityView;")?;
+    writeln!(out)?;
+    for serializer in serializers {
+        write_serializer(&mut out, serializer)?;
+    }
+    Ok(out)
+}
+
+// ----
+
+fn main() -> Result<()> {
+    let args: Vec<String> = std::env::args().collect();
+    let filepath = args.get(1);
+    if filepath.is_none() {
+        eprintln!("usage: entitybindings <filepath>");
+        std::process::exit(42);
+    }
+
+    let file = File::open(filepath.unwrap())?;
+    let buf_reader = BufReader::new(file);
+
+    let mut parser = Parser::from_reader(buf_reader)?;
+
+    parse_to_serializers(&mut parser)?;
+    let serializers = parser
+        .serializers()
+        .ok_or_else(|| anyhow!("could not get flattened serializer"))?;
+
+    let out = generate(serializers.values().map(|s| s.as_ref()))?;
+    std::io::stdout().lock().write_all(out.as_bytes())?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use expect_test::expect;
+    use haste::{
+        flattenedserializers::{FlattenedSerializerContainer, FlattenedSerializerContext},
+        protos::{
+            prost::Message, CDemoSendTables, CsvcMsgFlattenedSerializer,
+            ProtoFlattenedSerializerFieldT, ProtoFlattenedSerializerT,
+        },
+    };
+
+    #[test]
+    fn test_generate() -> Result<()> {
+        let symbols = [
+            "CEntityIdentity",
+            "CTestEntity",
+            "int32",
+            "m_nameStringableIndex",
+            "m_iScore",
+            "bool",
+            "m_bAlive",
+            "CUtlSymbolLarge",
+            "m_iszName",
+            "CEntityIdentity*",
+            "m_pEntity",
+            "int32[4]",
+            "m_iValues",
+            "CNetworkUtlVectorBase< int32 >",
+            "m_vecValues",
+        ];
+        let sym = |symbol: &str| symbols.iter().position(|s| *s == symbol).map(|i| i as i32);
+        let make_field = |var_type: &str, var_name: &str| ProtoFlattenedSerializerFieldT {
+            var_type_sym: sym(var_type),
+            var_name_sym: sym(var_name),
+            ..Default::default()
+        };
+
+        let msg = CsvcMsgFlattenedSerializer {
+            serializers: vec![
+                ProtoFlattenedSerializerT {
+                    serializer_name_sym: sym("CEntityIdentity"),
+                    serializer_version: Some(0),
+                    fields_index: vec![0],
+                },
+                ProtoFlattenedSerializerT {
+                    serializer_name_sym: sym("CTestEntity"),
+                    serializer_version: Some(0),
+                    fields_index: vec![1, 2, 3, 4, 5, 6],
+                },
+            ],
+            symbols: symbols.iter().map(|s| s.to_string()).collect(),
+            fields: vec![
+                make_field("int32", "m_nameStringableIndex"),
+                make_field("int32", "m_iScore"),
+                make_field("bool", "m_bAlive"),
+                make_field("CUtlSymbolLarge", "m_iszName"),
+                ProtoFlattenedSerializerFieldT {
+                    field_serializer_name_sym: sym("CEntityIdentity"),
+                    ..make_field("CEntityIdentity*", "m_pEntity")
+                },
+                make_field("int32[4]", "m_iValues"),
+                make_field("CNetworkUtlVectorBase< int32 >", "m_vecValues"),
+            ],
+        };
+        let serializers = FlattenedSerializerContainer::parse(
+            CDemoSendTables {
+                data: Some(msg.encode_length_delimited_to_vec()),
+            },
+            FlattenedSerializerContext {
+                tick_interval: 1.0 / 30.0,
+            },
+        )?;
+
+        let out = generate(serializers.values().map(|s| s.as_ref()))?;
+        expect![[r#"
+            // generated by entitybindings; do not edit.
+
+            #![allow(non_camel_case_types, non_snake_case, dead_code)]
+
+            use haste::EntityView;
+
+            #[derive(Debug, Clone, EntityView)]
+            #[haste(class = "CEntityIdentity")]
+            pub struct CEntityIdentity {
+                /// int32
+                #[haste(field = "m_nameStringableIndex")]
+                pub m_nameStringableIndex: i32,
+            }
+
+            impl CEntityIdentity {
+                pub const SERIALIZER_NAME: &'static str = "CEntityIdentity";
+            }
+
+            #[derive(Debug, Clone, EntityView)]
+            #[haste(class = "CTestEntity")]
+            pub struct CTestEntity<'a> {
+                /// int32
+                #[haste(field = "m_iScore")]
+                pub m_iScore: i32,
+                /// bool
+                #[haste(field = "m_bAlive")]
+                pub m_bAlive: bool,
+                /// CUtlSymbolLarge
+                #[haste(field = "m_iszName")]
+                pub m_iszName: &'a str,
+                /// int32
+                #[haste(field = "m_pEntity.m_nameStringableIndex")]
+                pub m_pEntity__m_nameStringableIndex: Option<i32>,
+                /// int32[4]
+                #[haste(field = "m_iValues")]
+                pub m_iValues: [i32; 4],
+                /// CNetworkUtlVectorBase< int32 >
+                #[haste(field = "m_vecValues")]
+                pub m_vecValues: Vec<i32>,
+            }
+
+            impl<'a> CTestEntity<'a> {
+                pub const SERIALIZER_NAME: &'static str = "CTestEntity";
+            }
+
+        "#]].assert_eq(&out);
+
+        Ok(())
+    }
+}
@@ -4,15 +4,15 @@ use std::{
     io::{BufReader, Read, Seek},
 };
 
-use anyhow::anyhow;
+use anyhow::{anyhow, Result};
 use haste::{
     flattenedserializers::FlattenedSerializerContainer,
-    parser::{ControlFlow, NopVisitor, Parser, Result},
+    parser::{ControlFlow, NopVisitor, Parser},
     protos::EDemoCommands,
 };
 use haste_vartype::{TokenKind, Tokenizer};
 
-fn parse_to_serializers<R: Read + Seek>(parser: &mut Parser<R, NopVisitor>) -> Result<()> {
+fn parse_to_serializers<R: Read + Seek>(parser: &mut Parser<R, NopVisitor>) -> haste::Result<()> {
     parser.reset()?;
     parser.run(|notnotself, cmd_header| {
         if notnotself.serializers().is_some() {